@@ -0,0 +1,35 @@
+//! Compares `basic::diff`, `basic::diff_myers` and `basic::diff_parallel` on a large input with a few edits.
+//! Run with `cargo bench --features rayon`.
+use prettydiff::basic;
+use std::time::Instant;
+
+fn bench<F: FnMut() -> usize>(name: &str, iterations: u32, mut f: F) {
+    let start = Instant::now();
+    let mut ops = 0;
+    for _ in 0..iterations {
+        ops = f();
+    }
+    println!(
+        "{:<14} {:>10.2?} per iteration ({} ops)",
+        name,
+        start.elapsed() / iterations,
+        ops
+    );
+}
+
+fn main() {
+    let old: Vec<String> = (0..10_000).map(|i| format!("line {}", i)).collect();
+    let mut new = old.clone();
+    for i in (0..new.len()).step_by(2_000) {
+        new[i] = format!("changed {}", i);
+    }
+    new.insert(5_000, "inserted".to_string());
+    new.remove(7_500);
+
+    bench("diff", 1, || basic::diff(&old, &new).len());
+    bench("diff_myers", 20, || basic::diff_myers(&old, &new).len());
+    #[cfg(feature = "rayon")]
+    bench("diff_parallel", 20, || {
+        basic::diff_parallel(&old, &new).len()
+    });
+}
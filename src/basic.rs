@@ -1,7 +1,12 @@
 //! Basic diff functions
 use crate::lcs;
+#[cfg(feature = "ansi")]
 use owo_colors::OwoColorize;
+use std::cmp::{max, min};
+use std::collections::HashMap;
 use std::fmt;
+use std::hash::Hash;
+use std::ops::Range;
 
 /// Single change in original slice needed to get new slice
 #[derive(Debug, PartialEq, Eq)]
@@ -16,34 +21,1230 @@ pub enum DiffOp<'a, T: 'a> {
     Equal(&'a [T]),
 }
 
-/// Diffs any slices which implements PartialEq
+impl<'a, T> Clone for DiffOp<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T> Copy for DiffOp<'a, T> {}
+
+impl<'a, T> DiffOp<'a, T> {
+    /// Copies elements into an [`OwnedDiffOp`], which does not borrow the diffed slices
+    pub fn into_owned(self) -> OwnedDiffOp<T::Owned>
+    where
+        T: ToOwned,
+    {
+        self.map(ToOwned::to_owned)
+    }
+
+    /// Converts every element with `f`, e.g. `op.map(|s| s.to_string())` for diffs of `&str`
+    pub fn map<U>(self, mut f: impl FnMut(&T) -> U) -> OwnedDiffOp<U> {
+        let mut convert = |a: &[T]| a.iter().map(&mut f).collect();
+        match self {
+            DiffOp::Insert(b) => OwnedDiffOp::Insert(convert(b)),
+            DiffOp::Replace(a, b) => OwnedDiffOp::Replace(convert(a), convert(b)),
+            DiffOp::Remove(a) => OwnedDiffOp::Remove(convert(a)),
+            DiffOp::Equal(a) => OwnedDiffOp::Equal(convert(a)),
+        }
+    }
+
+    /// Elements of the first slice covered by this op
+    pub fn old_slice(&self) -> &'a [T] {
+        match *self {
+            DiffOp::Insert(_) => &[],
+            DiffOp::Replace(a, _) | DiffOp::Remove(a) | DiffOp::Equal(a) => a,
+        }
+    }
+
+    /// Elements of the second slice covered by this op
+    pub fn new_slice(&self) -> &'a [T] {
+        match *self {
+            DiffOp::Remove(_) => &[],
+            DiffOp::Replace(_, b) | DiffOp::Insert(b) | DiffOp::Equal(b) => b,
+        }
+    }
+
+    /// Number of elements of the first slice covered by this op
+    pub fn old_len(&self) -> usize {
+        self.old_slice().len()
+    }
+
+    /// Number of elements of the second slice covered by this op
+    pub fn new_len(&self) -> usize {
+        self.new_slice().len()
+    }
+
+    pub fn is_equal(&self) -> bool {
+        matches!(self, DiffOp::Equal(_))
+    }
+
+    /// Anything but [`DiffOp::Equal`]
+    pub fn is_change(&self) -> bool {
+        !self.is_equal()
+    }
+
+    /// Name of the variant: `"insert"`, `"replace"`, `"remove"` or `"equal"`
+    pub fn tag(&self) -> &'static str {
+        match self {
+            DiffOp::Insert(_) => "insert",
+            DiffOp::Replace(..) => "replace",
+            DiffOp::Remove(_) => "remove",
+            DiffOp::Equal(_) => "equal",
+        }
+    }
+}
+
+impl<'a, T: fmt::Display> fmt::Display for DiffOp<'a, T> {
+    /// One element per line, prefixed with `-` if removed, `+` if inserted and a space if equal
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let lines: Vec<(char, &T)> = match self {
+            DiffOp::Equal(a) => a.iter().map(|i| (' ', i)).collect(),
+            _ => self
+                .old_slice()
+                .iter()
+                .map(|i| ('-', i))
+                .chain(self.new_slice().iter().map(|i| ('+', i)))
+                .collect(),
+        };
+        for (index, (prefix, i)) in lines.into_iter().enumerate() {
+            if index > 0 {
+                writeln!(formatter)?;
+            }
+            write!(formatter, "{}{}", prefix, i)?;
+        }
+        Ok(())
+    }
+}
+
+/// Owned version of [`DiffOp`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OwnedDiffOp<T> {
+    /// Appears only in second slice
+    Insert(Vec<T>),
+    /// Appears in both slices, but changed
+    Replace(Vec<T>, Vec<T>),
+    /// Appears only in first slice
+    Remove(Vec<T>),
+    /// Appears on both slices
+    Equal(Vec<T>),
+}
+
+impl<T> OwnedDiffOp<T> {
+    /// Borrows elements as [`DiffOp`], so owned ops can be formatted like fresh diffs
+    pub fn as_diff_op(&self) -> DiffOp<'_, T> {
+        match self {
+            OwnedDiffOp::Insert(b) => DiffOp::Insert(b),
+            OwnedDiffOp::Replace(a, b) => DiffOp::Replace(a, b),
+            OwnedDiffOp::Remove(a) => DiffOp::Remove(a),
+            OwnedDiffOp::Equal(a) => DiffOp::Equal(a),
+        }
+    }
+}
+
+/// Serialized form of [`DiffOp`] and [`OwnedDiffOp`], e.g. `{"op":"replace","old":[..],"new":[..]}`.
+/// Unchanged elements are `{"op":"equal","items":[..]}`
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+enum TaggedOp<S> {
+    Insert { new: S },
+    Replace { old: S, new: S },
+    Remove { old: S },
+    Equal { items: S },
+}
+
+#[cfg(feature = "serde")]
+impl<'a, T: serde::Serialize> serde::Serialize for DiffOp<'a, T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match *self {
+            DiffOp::Insert(new) => TaggedOp::Insert { new },
+            DiffOp::Replace(old, new) => TaggedOp::Replace { old, new },
+            DiffOp::Remove(old) => TaggedOp::Remove { old },
+            DiffOp::Equal(items) => TaggedOp::Equal { items },
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Same representation as [`DiffOp`]
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize> serde::Serialize for OwnedDiffOp<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.as_diff_op().serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: serde::Deserialize<'de>> serde::Deserialize<'de> for OwnedDiffOp<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(match TaggedOp::deserialize(deserializer)? {
+            TaggedOp::Insert { new } => OwnedDiffOp::Insert(new),
+            TaggedOp::Replace { old, new } => OwnedDiffOp::Replace(old, new),
+            TaggedOp::Remove { old } => OwnedDiffOp::Remove(old),
+            TaggedOp::Equal { items } => OwnedDiffOp::Equal(items),
+        })
+    }
+}
+
+/// Diffs any slices which implements PartialEq.
+/// Large inputs are diffed with [`lcs::lcs_linear`] in linear memory, which may align changes
+/// differently than the full LCS table. Identical and empty inputs skip the LCS.
+///
+/// ```
+/// use prettydiff::basic::{diff, DiffOp};
+///
+/// let old: &[u32] = &[1, 2, 3, 4];
+/// let new: &[u32] = &[1, 3, 4, 5];
+/// assert_eq!(
+///     diff(old, new),
+///     vec![
+///         DiffOp::Equal(&[1][..]),
+///         DiffOp::Remove(&[2][..]),
+///         DiffOp::Equal(&[3, 4][..]),
+///         DiffOp::Insert(&[5][..]),
+///     ]
+/// );
+/// ```
 pub fn diff<'a, T: PartialEq>(x: &'a [T], y: &'a [T]) -> Vec<DiffOp<'a, T>> {
-    let mut ops: Vec<DiffOp<T>> = Vec::new();
-    let table = lcs::Table::new(x, y);
+    diff_by(x, y, |a, b| a == b)
+}
+
+/// Diffs any slices, elements are compared with `eq`.
+/// Ops still refer to the original elements, so ignored differences are kept in the output.
+pub fn diff_by<'a, T, F>(x: &'a [T], y: &'a [T], eq: F) -> Vec<DiffOp<'a, T>>
+where
+    F: Fn(&T, &T) -> bool,
+{
+    DiffIter::new_by(x, y, eq).collect()
+}
+
+/// Same as [`diff`], with the ranges of `x` and `y` each op covers
+pub fn diff_indexed<'a, T: PartialEq>(
+    x: &'a [T],
+    y: &'a [T],
+) -> Vec<(Range<usize>, Range<usize>, DiffOp<'a, T>)> {
+    let (mut i, mut j) = (0, 0);
+    diff_iter(x, y)
+        .map(|op| {
+            let ranges = (i..i + op.old_len(), j..j + op.new_len());
+            i += op.old_len();
+            j += op.new_len();
+            (ranges.0, ranges.1, op)
+        })
+        .collect()
+}
+
+/// Lazily yields the ops of [`diff`], see [`diff_iter`]
+#[derive(Debug)]
+pub struct DiffIter<'a, T> {
+    x: &'a [T],
+    y: &'a [T],
+    matches: std::vec::IntoIter<(usize, usize, usize)>,
+    i: usize,
+    j: usize,
+    pending: Option<DiffOp<'a, T>>,
+}
+
+impl<'a, T> DiffIter<'a, T> {
+    fn new_by<F>(x: &'a [T], y: &'a [T], eq: F) -> Self
+    where
+        F: Fn(&T, &T) -> bool,
+    {
+        let mut matches: Vec<(usize, usize, usize)> = Vec::new();
+        // Identical inputs and inputs without common elements need no LCS, a scan for a common
+        // element stops at the first one and doesn't allocate the table
+        if x.len() == y.len() && x.iter().zip(y).all(|(a, b)| eq(a, b)) {
+            matches.push((0, 0, x.len()));
+        } else if x.iter().any(|a| y.iter().any(|b| eq(a, b))) {
+            for (i, j) in lcs::lcs_indices_by(x, y, eq) {
+                match matches.last_mut() {
+                    Some(last) if last.0 + last.2 == i && last.1 + last.2 == j => last.2 += 1,
+                    _ => matches.push((i, j, 1)),
+                }
+            }
+        }
+        matches.push((x.len(), y.len(), 0));
+        DiffIter {
+            x,
+            y,
+            matches: matches.into_iter(),
+            i: 0,
+            j: 0,
+            pending: None,
+        }
+    }
+}
+
+impl<'a, T> Iterator for DiffIter<'a, T> {
+    type Item = DiffOp<'a, T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(op) = self.pending.take() {
+            return Some(op);
+        }
+        for (x, y, len) in self.matches.by_ref() {
+            let removed = &self.x[self.i..x];
+            let inserted = &self.y[self.j..y];
+            let equal = if len > 0 {
+                Some(DiffOp::Equal(&self.x[x..x + len]))
+            } else {
+                None
+            };
+            self.i = x + len;
+            self.j = y + len;
+            let change = match (removed.is_empty(), inserted.is_empty()) {
+                (true, true) => None,
+                (false, true) => Some(DiffOp::Remove(removed)),
+                (true, false) => Some(DiffOp::Insert(inserted)),
+                (false, false) => Some(DiffOp::Replace(removed, inserted)),
+            };
+            match change {
+                Some(change) => {
+                    self.pending = equal;
+                    return Some(change);
+                }
+                None if equal.is_some() => return equal,
+                None => {}
+            }
+        }
+        None
+    }
+}
+
+/// Same as [`diff`], but yields ops one by one.
+/// The LCS table is still computed upfront, the iterator saves allocating all ops and
+/// borrows `x` and `y` for its whole lifetime.
+pub fn diff_iter<'a, T: PartialEq>(x: &'a [T], y: &'a [T]) -> DiffIter<'a, T> {
+    DiffIter::new_by(x, y, |a, b| a == b)
+}
+
+/// Diffs slices with the greedy Myers algorithm.
+///
+/// Takes O((N + M) * D) time and O(D²) memory, where D is the number of inserted and removed
+/// elements, so it is much faster than [`diff`] (O(N * M) time and memory) on large inputs
+/// with few changes, and slower on completely different ones.
+/// Results are grouped the same way, but when several diffs of the same size exist
+/// the two algorithms may choose different ones.
+pub fn diff_myers<'a, T: PartialEq>(x: &'a [T], y: &'a [T]) -> Vec<DiffOp<'a, T>> {
+    diff_with_limit(x, y, x.len() + y.len()).expect("no diff needs more edits than elements")
+}
+
+/// Same as [`diff_myers`], but gives up and returns `None` once more than `max_cost` inserted
+/// and removed elements are needed. Takes at most O((N + M) * max_cost) time and
+/// O(max_cost²) memory, so untrusted inputs can't cause a quadratic blowup.
+pub fn diff_with_limit<'a, T: PartialEq>(
+    x: &'a [T],
+    y: &'a [T],
+    max_cost: usize,
+) -> Option<Vec<DiffOp<'a, T>>> {
+    let (n, m) = (x.len() as isize, y.len() as isize);
+    let max_cost = min(max_cost, x.len() + y.len()) as isize;
+    // `trace[d]` holds furthest x reached on diagonals `-d..=d` after `d` edits
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+    let mut v = vec![0isize; 1];
+    let mut found = false;
+    'search: for d in 0..=max_cost {
+        let mut next = vec![0isize; 2 * d as usize + 1];
+        for k in (-d..=d).step_by(2) {
+            let prev = |k: isize| v[(k + d - 1) as usize];
+            let mut i = if d == 0 {
+                0
+            } else if k == -d || (k != d && prev(k - 1) < prev(k + 1)) {
+                prev(k + 1)
+            } else {
+                prev(k - 1) + 1
+            };
+            let mut j = i - k;
+            while i < n && j < m && x[i as usize] == y[j as usize] {
+                i += 1;
+                j += 1;
+            }
+            next[(k + d) as usize] = i;
+            if i >= n && j >= m {
+                trace.push(next);
+                found = true;
+                break 'search;
+            }
+        }
+        trace.push(next);
+        v = trace.last().unwrap().clone();
+    }
+    if !found {
+        return None;
+    }
+
+    // Walk back from the end, collecting runs as (equal, removed, inserted) in reverse
+    let mut steps = Vec::with_capacity(trace.len());
+    let (mut i, mut j) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = i - j;
+        let (prev_i, prev_j) = if d == 0 {
+            (0, 0)
+        } else {
+            let prev = |k: isize| trace[d as usize - 1][(k + d - 1) as usize];
+            let prev_k = if k == -d || (k != d && prev(k - 1) < prev(k + 1)) {
+                k + 1
+            } else {
+                k - 1
+            };
+            (prev(prev_k), prev(prev_k) - prev_k)
+        };
+        debug_assert_eq!(v[(k + d) as usize], i);
+        let snake = min(i - prev_i, j - prev_j);
+        steps.push((
+            snake as usize,
+            (i - snake - prev_i) as usize,
+            (j - snake - prev_j) as usize,
+        ));
+        i = prev_i;
+        j = prev_j;
+    }
+
+    let mut ops = OpsBuilder::new(x, y);
+    for (equal, removed, inserted) in steps.into_iter().rev() {
+        ops.change(removed, inserted);
+        ops.equal(equal);
+    }
+    Some(ops.finish())
+}
+
+/// Diffs slices with the patience algorithm: elements which occur exactly once in both inputs
+/// are matched up as anchors and the gaps between them are diffed recursively, gaps without
+/// such elements fall back to [`diff`].
+/// Usually gives more readable diffs of source code, where lines like `}` or blank lines
+/// otherwise get matched across unrelated blocks.
+pub fn diff_patience<'a, T: Hash + Eq>(x: &'a [T], y: &'a [T]) -> Vec<DiffOp<'a, T>> {
+    let mut ops = OpsBuilder::new(x, y);
+    patience(x, y, &mut ops);
+    ops.finish()
+}
+
+fn patience<'a, T: Hash + Eq>(x: &'a [T], y: &'a [T], ops: &mut OpsBuilder<'a, T>) {
+    let prefix = x.iter().zip(y).take_while(|(a, b)| a == b).count();
+    let (x_rest, y_rest) = (&x[prefix..], &y[prefix..]);
+    let suffix = x_rest
+        .iter()
+        .rev()
+        .zip(y_rest.iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let x_mid = &x_rest[..x_rest.len() - suffix];
+    let y_mid = &y_rest[..y_rest.len() - suffix];
+    ops.equal(prefix);
+
+    let anchors = unique_anchors(x_mid, y_mid);
+    if anchors.is_empty() {
+        for op in diff(x_mid, y_mid) {
+            match op {
+                DiffOp::Equal(a) => ops.equal(a.len()),
+                DiffOp::Insert(b) => ops.change(0, b.len()),
+                DiffOp::Remove(a) => ops.change(a.len(), 0),
+                DiffOp::Replace(a, b) => ops.change(a.len(), b.len()),
+            }
+        }
+    } else {
+        let (mut i, mut j) = (0, 0);
+        for (ai, aj) in anchors {
+            patience(&x_mid[i..ai], &y_mid[j..aj], ops);
+            ops.equal(1);
+            i = ai + 1;
+            j = aj + 1;
+        }
+        patience(&x_mid[i..], &y_mid[j..], ops);
+    }
+    ops.equal(suffix);
+}
+
+/// Gaps of [`diff_parallel`] needing fewer element comparisons than this in total are diffed
+/// on the calling thread, handing them to other threads would take longer
+#[cfg(feature = "rayon")]
+const PARALLEL_MIN_WORK: usize = 1 << 16;
+
+/// Splits the inputs on elements which occur exactly once in both, chosen like in
+/// [`diff_patience`], and diffs the gaps between them with [`diff`] on multiple threads with
+/// `rayon`, enabled by the `rayon` feature. Small inputs are diffed on the calling thread.
+///
+/// The result is the same as diffing the gaps one after another, but not always the same as
+/// [`diff`] of the whole inputs: an anchor can cost more equal elements than it saves, e.g.
+/// `[u, a, a, a]` and `[a, a, a, u]` anchored on `u` keep one equal element instead of three.
+#[cfg(feature = "rayon")]
+pub fn diff_parallel<'a, T: Hash + Eq + Sync>(x: &'a [T], y: &'a [T]) -> Vec<DiffOp<'a, T>> {
+    diff_anchored(x, y, PARALLEL_MIN_WORK)
+}
+
+/// [`diff_parallel`], with gaps diffed in parallel if they need at least `min_parallel_work`
+/// element comparisons in total
+#[cfg(feature = "rayon")]
+fn diff_anchored<'a, T: Hash + Eq + Sync>(
+    x: &'a [T],
+    y: &'a [T],
+    min_parallel_work: usize,
+) -> Vec<DiffOp<'a, T>> {
+    use rayon::prelude::*;
+
+    let mut segments = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    for (ai, aj) in unique_anchors(x, y) {
+        segments.push((&x[i..ai], &y[j..aj]));
+        i = ai + 1;
+        j = aj + 1;
+    }
+    segments.push((&x[i..], &y[j..]));
+
+    let work = segments
+        .iter()
+        .map(|(a, b)| a.len().saturating_mul(b.len()))
+        .fold(0, usize::saturating_add);
+    let diffs: Vec<_> = if work >= min_parallel_work {
+        segments.par_iter().map(|(a, b)| diff(a, b)).collect()
+    } else {
+        segments.iter().map(|(a, b)| diff(a, b)).collect()
+    };
+
+    let mut ops = OpsBuilder::new(x, y);
+    for (index, segment) in diffs.into_iter().enumerate() {
+        if index > 0 {
+            ops.equal(1);
+        }
+        for op in segment {
+            match op {
+                DiffOp::Equal(a) => ops.equal(a.len()),
+                DiffOp::Insert(b) => ops.change(0, b.len()),
+                DiffOp::Remove(a) => ops.change(a.len(), 0),
+                DiffOp::Replace(a, b) => ops.change(a.len(), b.len()),
+            }
+        }
+    }
+    ops.finish()
+}
+
+/// Longest increasing sequence of (x, y) positions of elements unique in both `x` and `y`
+fn unique_anchors<T: Hash + Eq>(x: &[T], y: &[T]) -> Vec<(usize, usize)> {
+    let mut counts: HashMap<&T, (usize, usize, usize, usize)> = HashMap::new();
+    for (i, a) in x.iter().enumerate() {
+        let entry = counts.entry(a).or_insert((0, 0, i, 0));
+        entry.0 += 1;
+    }
+    for (j, b) in y.iter().enumerate() {
+        if let Some(entry) = counts.get_mut(b) {
+            entry.1 += 1;
+            entry.3 = j;
+        }
+    }
+    let mut unique: Vec<(usize, usize)> = counts
+        .into_values()
+        .filter(|&(in_x, in_y, _, _)| in_x == 1 && in_y == 1)
+        .map(|(_, _, i, j)| (i, j))
+        .collect();
+    unique.sort_unstable();
+
+    // Patience sorting: `piles` holds the index of the top card of each pile,
+    // `back` the top of the previous pile when each card was placed
+    let mut piles: Vec<usize> = Vec::new();
+    let mut back = vec![None; unique.len()];
+    for (card, &(_, j)) in unique.iter().enumerate() {
+        let pile = piles.partition_point(|&top| unique[top].1 < j);
+        back[card] = pile.checked_sub(1).map(|prev| piles[prev]);
+        if pile == piles.len() {
+            piles.push(card);
+        } else {
+            piles[pile] = card;
+        }
+    }
+    let mut anchors = Vec::with_capacity(piles.len());
+    let mut card = piles.last().copied();
+    while let Some(current) = card {
+        anchors.push(unique[current]);
+        card = back[current];
+    }
+    anchors.reverse();
+    anchors
+}
+
+/// Diffs inputs window by window, so memory is bounded by `window`² instead of `x.len() * y.len()`.
+/// Changes after the last equal run of a window are diffed again as part of the next window,
+/// the result is close to, but not always as small as, the one of [`diff`].
+pub fn diff_chunked<'a, T: PartialEq>(x: &'a [T], y: &'a [T], window: usize) -> Vec<DiffOp<'a, T>> {
+    diff_chunked_by(x, y, window, |a, b| a == b)
+}
+
+/// Same as [`diff_chunked`], elements are compared with `eq`
+pub(crate) fn diff_chunked_by<'a, T, F>(
+    x: &'a [T],
+    y: &'a [T],
+    window: usize,
+    eq: F,
+) -> Vec<DiffOp<'a, T>>
+where
+    F: Fn(&T, &T) -> bool,
+{
+    let window = max(window, 1);
+    let mut ops = OpsBuilder::new(x, y);
+    let mut i = 0;
+    let mut j = 0;
+    while i < x.len() || j < y.len() {
+        let x_end = min(i + window, x.len());
+        let y_end = min(j + window, y.len());
+        let chunk = diff_by(&x[i..x_end], &y[j..y_end], &eq);
+        let commit = if x_end == x.len() && y_end == y.len() {
+            chunk.len()
+        } else {
+            chunk
+                .iter()
+                .rposition(|op| matches!(op, DiffOp::Equal(_)))
+                .map_or(chunk.len(), |last| last + 1)
+        };
+        for op in &chunk[..commit] {
+            let (removed, inserted) = match op {
+                DiffOp::Equal(a) => (a.len(), a.len()),
+                DiffOp::Insert(b) => (0, b.len()),
+                DiffOp::Remove(a) => (a.len(), 0),
+                DiffOp::Replace(a, b) => (a.len(), b.len()),
+            };
+            if let DiffOp::Equal(_) = op {
+                ops.equal(removed);
+            } else {
+                ops.change(removed, inserted);
+            }
+            i += removed;
+            j += inserted;
+        }
+    }
+    ops.finish()
+}
+
+/// Builds ops out of consecutive equal runs and changes, adjacent runs of the same kind are merged
+pub(crate) struct OpsBuilder<'a, T> {
+    x: &'a [T],
+    y: &'a [T],
+    ops: Vec<DiffOp<'a, T>>,
+    i: usize,
+    j: usize,
+    equal: usize,
+    removed: usize,
+    inserted: usize,
+}
+
+impl<'a, T> OpsBuilder<'a, T> {
+    pub(crate) fn new(x: &'a [T], y: &'a [T]) -> Self {
+        OpsBuilder {
+            x,
+            y,
+            ops: Vec::new(),
+            i: 0,
+            j: 0,
+            equal: 0,
+            removed: 0,
+            inserted: 0,
+        }
+    }
+
+    /// Next `len` elements are equal on both sides
+    pub(crate) fn equal(&mut self, len: usize) {
+        if len > 0 {
+            self.flush_change();
+            self.equal += len;
+        }
+    }
+
+    /// Next `removed` elements of x are replaced by next `inserted` elements of y
+    pub(crate) fn change(&mut self, removed: usize, inserted: usize) {
+        if removed > 0 || inserted > 0 {
+            self.flush_equal();
+            self.removed += removed;
+            self.inserted += inserted;
+        }
+    }
+
+    fn flush_equal(&mut self) {
+        if self.equal > 0 {
+            self.ops
+                .push(DiffOp::Equal(&self.x[self.i..self.i + self.equal]));
+            self.i += self.equal;
+            self.j += self.equal;
+            self.equal = 0;
+        }
+    }
+
+    fn flush_change(&mut self) {
+        let x_seq = &self.x[self.i..self.i + self.removed];
+        let y_seq = &self.y[self.j..self.j + self.inserted];
+        if self.removed > 0 && self.inserted > 0 {
+            self.ops.push(DiffOp::Replace(x_seq, y_seq));
+        } else if self.removed > 0 {
+            self.ops.push(DiffOp::Remove(x_seq));
+        } else if self.inserted > 0 {
+            self.ops.push(DiffOp::Insert(y_seq));
+        }
+        self.i += self.removed;
+        self.j += self.inserted;
+        self.removed = 0;
+        self.inserted = 0;
+    }
+
+    pub(crate) fn finish(mut self) -> Vec<DiffOp<'a, T>> {
+        self.flush_equal();
+        self.flush_change();
+        self.ops
+    }
+}
+
+/// Where to slide an insertion or removal which could be placed at several positions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShiftDirection {
+    /// Keep placement chosen by the diff algorithm
+    None,
+    /// Move changes as close to the beginning as possible
+    Up,
+    /// Move changes as close to the end as possible
+    Down,
+}
+
+/// Slides every `Insert` and `Remove` within its movable range in `direction`.
+/// A change is movable when the element leaving it equals the element entering it,
+/// like `a, b` inserted into `a, b, c` which fits both before and after the existing `a, b`.
+pub fn shift_changes<'a, T: PartialEq>(
+    x: &'a [T],
+    y: &'a [T],
+    ops: Vec<DiffOp<'a, T>>,
+    direction: ShiftDirection,
+) -> Vec<DiffOp<'a, T>> {
+    if direction == ShiftDirection::None {
+        return ops;
+    }
+    // Lengths of (equal run before change, removed, inserted) with a trailing equal run
+    let mut runs: Vec<(usize, usize, usize)> = Vec::new();
+    let mut tail = 0;
+    for op in &ops {
+        match op {
+            DiffOp::Equal(a) => tail += a.len(),
+            DiffOp::Insert(b) => runs.push((std::mem::take(&mut tail), 0, b.len())),
+            DiffOp::Remove(a) => runs.push((std::mem::take(&mut tail), a.len(), 0)),
+            DiffOp::Replace(a, b) => runs.push((std::mem::take(&mut tail), a.len(), b.len())),
+        }
+    }
+    let mut i = 0;
+    let mut j = 0;
+    for k in 0..runs.len() {
+        let (before, removed, inserted) = runs[k];
+        i += before;
+        j += before;
+        let after = runs.get(k + 1).map_or(tail, |run| run.0);
+        // Only pure insertions and removals are movable
+        let movable = match (removed, inserted) {
+            (0, len) => Some((y, j, len)),
+            (len, 0) => Some((x, i, len)),
+            _ => None,
+        };
+        let mut shift = 0;
+        if let Some((seq, start, len)) = movable {
+            match direction {
+                ShiftDirection::Up => {
+                    while shift < before && seq[start - shift - 1] == seq[start - shift - 1 + len] {
+                        shift += 1;
+                    }
+                }
+                ShiftDirection::Down => {
+                    while shift < after && seq[start + shift] == seq[start + shift + len] {
+                        shift += 1;
+                    }
+                }
+                ShiftDirection::None => {}
+            }
+        }
+        let next = match runs.get_mut(k + 1) {
+            Some(next) => &mut next.0,
+            None => &mut tail,
+        };
+        if direction == ShiftDirection::Up {
+            *next += shift;
+            runs[k].0 -= shift;
+            i -= shift;
+            j -= shift;
+        } else {
+            *next -= shift;
+            runs[k].0 += shift;
+            i += shift;
+            j += shift;
+        }
+        i += removed;
+        j += inserted;
+    }
+
+    let mut builder = OpsBuilder::new(x, y);
+    for (before, removed, inserted) in runs {
+        builder.equal(before);
+        builder.change(removed, inserted);
+    }
+    builder.equal(tail);
+    builder.finish()
+}
+
+/// Group of changes with surrounding context, see [`into_hunks`]
+#[derive(Debug, PartialEq, Eq)]
+pub struct Hunk<'a, T> {
+    /// Index of the first old element in the hunk
+    pub old_start: usize,
+    /// Number of old elements in the hunk
+    pub old_len: usize,
+    /// Index of the first new element in the hunk
+    pub new_start: usize,
+    /// Number of new elements in the hunk
+    pub new_len: usize,
+    /// Changes of the hunk with their context
+    pub ops: Vec<DiffOp<'a, T>>,
+}
+
+impl<'a, T> Hunk<'a, T> {
+    fn push(&mut self, op: DiffOp<'a, T>) {
+        self.old_len += op.old_len();
+        self.new_len += op.new_len();
+        self.ops.push(op);
+    }
+
+    /// Number of removed and inserted elements
+    pub fn change_count(&self) -> usize {
+        self.old_len + self.new_len - 2 * self.equal_count()
+    }
+
+    /// Share of unchanged elements as `2 * equal / (old_len + new_len)`, from 0.0 to 1.0
+    pub fn similarity(&self) -> f64 {
+        if self.old_len + self.new_len == 0 {
+            return 1.0;
+        }
+        2.0 * self.equal_count() as f64 / (self.old_len + self.new_len) as f64
+    }
+
+    fn equal_count(&self) -> usize {
+        self.ops
+            .iter()
+            .map(|op| match op {
+                DiffOp::Equal(a) => a.len(),
+                _ => 0,
+            })
+            .sum()
+    }
+}
+
+/// Groups changes into hunks with up to `context` equal elements around them.
+/// Changes separated by at most `2 * context` equal elements end up in the same hunk.
+/// Context is cut at the start and end of the inputs, so hunks never reach past them.
+pub fn into_hunks<'a, T>(ops: &[DiffOp<'a, T>], context: usize) -> Vec<Hunk<'a, T>> {
+    let mut hunks = Vec::new();
+    let mut current: Option<Hunk<T>> = None;
+    let mut i = 0;
+    let mut j = 0;
+    for (index, op) in ops.iter().enumerate() {
+        if let DiffOp::Equal(a) = op {
+            if let Some(mut hunk) = current.take() {
+                if index + 1 < ops.len() && a.len() <= 2 * context {
+                    hunk.push(*op);
+                    current = Some(hunk);
+                } else {
+                    if context > 0 {
+                        hunk.push(DiffOp::Equal(&a[..min(context, a.len())]));
+                    }
+                    hunks.push(hunk);
+                }
+            }
+            i += a.len();
+            j += a.len();
+            continue;
+        }
+        let hunk = current.get_or_insert_with(|| {
+            let before = match index.checked_sub(1).map(|k| ops[k]) {
+                Some(DiffOp::Equal(a)) => &a[a.len() - min(context, a.len())..],
+                _ => &[],
+            };
+            let mut hunk = Hunk {
+                old_start: i - before.len(),
+                old_len: 0,
+                new_start: j - before.len(),
+                new_len: 0,
+                ops: Vec::new(),
+            };
+            if !before.is_empty() {
+                hunk.push(DiffOp::Equal(before));
+            }
+            hunk
+        });
+        hunk.push(*op);
+        match op {
+            DiffOp::Insert(b) => j += b.len(),
+            DiffOp::Remove(a) => i += a.len(),
+            DiffOp::Replace(a, b) => {
+                i += a.len();
+                j += b.len();
+            }
+            DiffOp::Equal(_) => {}
+        }
+    }
+    hunks.extend(current);
+    hunks
+}
+
+/// Detects moved blocks: pairs up `Remove` and `Insert` ops of `ops` with identical content,
+/// returns `(remove, insert)` op indices. Each insert is matched at most once
+pub fn detect_moves<T: PartialEq>(ops: &[DiffOp<T>]) -> Vec<(usize, usize)> {
+    let mut moves = Vec::new();
+    let mut used = vec![false; ops.len()];
+    for (i, op) in ops.iter().enumerate() {
+        if let DiffOp::Remove(a) = op {
+            let found = ops.iter().enumerate().position(|(j, other)| match other {
+                DiffOp::Insert(b) => !used[j] && a == b,
+                _ => false,
+            });
+            if let Some(j) = found {
+                used[j] = true;
+                moves.push((i, j));
+            }
+        }
+    }
+    moves
+}
+
+/// Counts of changed elements, see [`stats`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiffStats {
+    /// Inserted elements, including the new side of replaces
+    pub insertions: usize,
+    /// Removed elements, including the old side of replaces
+    pub deletions: usize,
+    /// Elements present in both inputs
+    pub unchanged: usize,
+    /// Elements removed in one place and inserted unchanged in another
+    pub moved: usize,
+    /// Replaced (modified) blocks, their elements also count as insertions and deletions
+    pub replacements: usize,
+}
+
+impl fmt::Display for DiffStats {
+    /// Formats as `+insertions -deletions`, followed by `→moved` if there are moved elements
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "+{} -{}", self.insertions, self.deletions)?;
+        if self.moved > 0 {
+            write!(formatter, " →{}", self.moved)?;
+        }
+        Ok(())
+    }
+}
+
+impl DiffStats {
+    /// Summary line like `+3 -1, 1 modified, 2 moved` with insertions in green and deletions in red
+    #[cfg(feature = "ansi")]
+    pub fn colored_summary(&self) -> String {
+        let mut out = format!(
+            "{} {}",
+            format!("+{}", self.insertions).green(),
+            format!("-{}", self.deletions).red()
+        );
+        if self.replacements > 0 {
+            out.push_str(&format!(", {} modified", self.replacements));
+        }
+        if self.moved > 0 {
+            out.push_str(&format!(", {} moved", self.moved));
+        }
+        out
+    }
+
+    /// [`colored_summary`](Self::colored_summary) preceded by file and hunk counts, like
+    /// `2 files changed, 3 hunks, +3 -1`
+    #[cfg(feature = "ansi")]
+    pub fn colored_summary_with(&self, files: usize, hunks: usize) -> String {
+        let count =
+            |n: usize, noun: &str| format!("{} {}{}", n, noun, if n == 1 { "" } else { "s" });
+        format!(
+            "{} changed, {}, {}",
+            count(files, "file"),
+            count(hunks, "hunk"),
+            self.colored_summary()
+        )
+    }
+}
+
+/// Similarity of slices as `2 * matches / (x.len() + y.len())`, like difflib's `SequenceMatcher.ratio`.
+/// Two empty slices are identical (1.0).
+pub fn ratio<T: PartialEq>(x: &[T], y: &[T]) -> f64 {
+    let total = x.len() + y.len();
+    if total == 0 {
+        return 1.0;
+    }
+    2.0 * lcs::lcs_len(x, y) as f64 / total as f64
+}
+
+/// Number of single element insertions and removals needed to turn `x` into `y`
+pub fn edit_distance<T: PartialEq>(x: &[T], y: &[T]) -> usize {
+    x.len() + y.len() - 2 * lcs::lcs_len(x, y)
+}
+
+/// Same as [`ratio`] for already computed `ops`
+pub(crate) fn ops_ratio<T>(ops: &[DiffOp<T>]) -> f64 {
+    let (mut equal, mut total) = (0, 0);
+    for op in ops {
+        match op {
+            DiffOp::Equal(a) => {
+                equal += a.len();
+                total += 2 * a.len();
+            }
+            DiffOp::Insert(b) => total += b.len(),
+            DiffOp::Remove(a) => total += a.len(),
+            DiffOp::Replace(a, b) => total += a.len() + b.len(),
+        }
+    }
+    if total == 0 {
+        return 1.0;
+    }
+    2.0 * equal as f64 / total as f64
+}
+
+/// Swaps old and new sides of `ops`, e.g. to undo a diff with [`apply`]
+pub fn reverse<T>(ops: Vec<DiffOp<T>>) -> Vec<DiffOp<T>> {
+    ops.into_iter()
+        .map(|op| match op {
+            DiffOp::Insert(b) => DiffOp::Remove(b),
+            DiffOp::Remove(a) => DiffOp::Insert(a),
+            DiffOp::Replace(a, b) => DiffOp::Replace(b, a),
+            DiffOp::Equal(a) => DiffOp::Equal(a),
+        })
+        .collect()
+}
+
+/// Rewrites every `Replace(a, b)` of `ops` into `Remove(a)` followed by `Insert(b)`, for
+/// consumers which only handle equal, removed and inserted elements
+pub fn split_replaces<T>(ops: Vec<DiffOp<T>>) -> Vec<DiffOp<T>> {
+    let mut out = Vec::with_capacity(ops.len());
+    for op in ops {
+        match op {
+            DiffOp::Replace(a, b) => {
+                out.push(DiffOp::Remove(a));
+                out.push(DiffOp::Insert(b));
+            }
+            op => out.push(op),
+        }
+    }
+    out
+}
+
+/// Part of a three-way merge, see [`merge3`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MergeChunk<T> {
+    /// Unchanged, changed on one side only or changed the same way on both sides
+    Resolved(Vec<T>),
+    /// Changed differently on both sides
+    Conflict {
+        base: Vec<T>,
+        left: Vec<T>,
+        right: Vec<T>,
+    },
+}
+
+/// Result of [`merge3`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MergeResult<T> {
+    /// Merged elements
+    Clean(Vec<T>),
+    /// All chunks in order, at least one of them is a conflict
+    Conflicts(Vec<MergeChunk<T>>),
+}
+
+/// Replacement of `base[start..end]` by `new`
+struct Edit<'a, T> {
+    start: usize,
+    end: usize,
+    new: &'a [T],
+    left: bool,
+}
+
+fn edits<'a, T: PartialEq>(base: &'a [T], other: &'a [T], left: bool) -> Vec<Edit<'a, T>> {
+    let mut edits = Vec::new();
+    let mut position = 0;
+    for op in diff(base, other) {
+        let (removed, new) = match op {
+            DiffOp::Equal(a) => {
+                position += a.len();
+                continue;
+            }
+            DiffOp::Insert(b) => (0, b),
+            DiffOp::Remove(a) => (a.len(), &[][..]),
+            DiffOp::Replace(a, b) => (a.len(), b),
+        };
+        edits.push(Edit {
+            start: position,
+            end: position + removed,
+            new,
+            left,
+        });
+        position += removed;
+    }
+    edits
+}
+
+/// Applies `edits` of one side to `base[start..end]`
+fn merge_side<T: Clone>(base: &[T], start: usize, end: usize, edits: &[&Edit<T>]) -> Vec<T> {
+    let mut out = Vec::new();
+    let mut position = start;
+    for edit in edits {
+        out.extend_from_slice(&base[position..edit.start]);
+        out.extend_from_slice(edit.new);
+        position = edit.end;
+    }
+    out.extend_from_slice(&base[position..end]);
+    out
+}
+
+/// Three-way merge of `left` and `right`, which are both based on `base`.
+/// Changes of both sides which overlap or touch are merged if they are the same,
+/// otherwise they are reported as [`MergeChunk::Conflict`].
+pub fn merge3<T: PartialEq + Clone>(base: &[T], left: &[T], right: &[T]) -> MergeResult<T> {
+    let mut all = edits(base, left, true);
+    all.extend(edits(base, right, false));
+    all.sort_by_key(|edit| (edit.start, edit.end));
+
+    let mut chunks = Vec::new();
+    let mut position = 0;
+    let mut rest = &all[..];
+    while let Some(first) = rest.first() {
+        let mut end = first.end;
+        let count = rest
+            .iter()
+            .take_while(|edit| {
+                let overlaps = edit.start <= end;
+                if overlaps {
+                    end = max(end, edit.end);
+                }
+                overlaps
+            })
+            .count();
+        let (group, tail) = rest.split_at(count);
+        rest = tail;
+
+        let start = first.start;
+        if position < start {
+            chunks.push(MergeChunk::Resolved(base[position..start].to_vec()));
+        }
+        let left_edits: Vec<_> = group.iter().filter(|edit| edit.left).collect();
+        let right_edits: Vec<_> = group.iter().filter(|edit| !edit.left).collect();
+        let left_side = merge_side(base, start, end, &left_edits);
+        let right_side = merge_side(base, start, end, &right_edits);
+        if right_edits.is_empty() || left_side == right_side {
+            if !left_side.is_empty() {
+                chunks.push(MergeChunk::Resolved(left_side));
+            }
+        } else if left_edits.is_empty() {
+            if !right_side.is_empty() {
+                chunks.push(MergeChunk::Resolved(right_side));
+            }
+        } else {
+            chunks.push(MergeChunk::Conflict {
+                base: base[start..end].to_vec(),
+                left: left_side,
+                right: right_side,
+            });
+        }
+        position = end;
+    }
+    if position < base.len() {
+        chunks.push(MergeChunk::Resolved(base[position..].to_vec()));
+    }
 
-    let mut i = 0;
-    let mut j = 0;
+    if chunks
+        .iter()
+        .any(|chunk| matches!(chunk, MergeChunk::Conflict { .. }))
+    {
+        return MergeResult::Conflicts(chunks);
+    }
+    let mut merged = Vec::new();
+    for chunk in chunks {
+        if let MergeChunk::Resolved(elements) = chunk {
+            merged.extend(elements);
+        }
+    }
+    MergeResult::Clean(merged)
+}
 
-    for m in table.matches_zero() {
-        let x_seq = &x[i..m.x];
-        let y_seq = &y[j..m.y];
+/// Error of [`apply`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApplyError {
+    /// `Equal`, `Remove` or `Replace` op doesn't match old elements starting at `position`
+    Mismatch { position: usize },
+    /// Ops end before old elements starting at `position`
+    Unconsumed { position: usize },
+}
 
-        if i < m.x && j < m.y {
-            ops.push(DiffOp::Replace(x_seq, y_seq));
-        } else if i < m.x {
-            ops.push(DiffOp::Remove(x_seq));
-        } else if j < m.y {
-            ops.push(DiffOp::Insert(y_seq));
+impl fmt::Display for ApplyError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ApplyError::Mismatch { position } => {
+                write!(formatter, "diff doesn't match old elements at {}", position)
+            }
+            ApplyError::Unconsumed { position } => {
+                write!(formatter, "diff ends before old elements at {}", position)
+            }
         }
+    }
+}
+
+impl std::error::Error for ApplyError {}
 
-        i = m.x + m.len;
-        j = m.y + m.len;
+/// Rebuilds the new side of a diff by replaying `ops` on `old`.
+/// Fails if ops expect other old elements than the ones found in `old`.
+pub fn apply<T: PartialEq + Clone>(old: &[T], ops: &[DiffOp<T>]) -> Result<Vec<T>, ApplyError> {
+    let mut new = Vec::with_capacity(old.len());
+    let mut position = 0;
+    let mut consume = |expected: &[T]| {
+        let end = position + expected.len();
+        if old.get(position..end) != Some(expected) {
+            return Err(ApplyError::Mismatch { position });
+        }
+        position = end;
+        Ok(())
+    };
+    for op in ops {
+        match op {
+            DiffOp::Equal(a) => {
+                consume(a)?;
+                new.extend_from_slice(a);
+            }
+            DiffOp::Insert(b) => new.extend_from_slice(b),
+            DiffOp::Remove(a) => consume(a)?,
+            DiffOp::Replace(a, b) => {
+                consume(a)?;
+                new.extend_from_slice(b);
+            }
+        }
+    }
+    if position < old.len() {
+        return Err(ApplyError::Unconsumed { position });
+    }
+    Ok(new)
+}
 
-        if m.len > 0 {
-            ops.push(DiffOp::Equal(&x[m.x..i]));
+/// Counts elements of `ops`, moved blocks count as `moved` instead of insertions and deletions.
+/// A `Replace(a, b)` counts `a.len()` deletions, `b.len()` insertions and one replacement
+pub fn stats<T: PartialEq>(ops: &[DiffOp<T>]) -> DiffStats {
+    let moves = detect_moves(ops);
+    let mut stats = DiffStats::default();
+    for (index, op) in ops.iter().enumerate() {
+        if moves.iter().any(|&(_, insert)| insert == index) {
+            continue;
+        }
+        match op {
+            DiffOp::Remove(a) if moves.iter().any(|&(remove, _)| remove == index) => {
+                stats.moved += a.len()
+            }
+            DiffOp::Equal(a) => stats.unchanged += a.len(),
+            DiffOp::Insert(b) => stats.insertions += b.len(),
+            DiffOp::Remove(a) => stats.deletions += a.len(),
+            DiffOp::Replace(a, b) => {
+                stats.deletions += a.len();
+                stats.insertions += b.len();
+                stats.replacements += 1;
+            }
         }
     }
-    ops
+    stats
 }
 
 /// Container for slice diff result.  Can be pretty-printed by Display trait.
@@ -52,6 +1253,27 @@ pub struct SliceChangeset<'a, T> {
     pub diff: Vec<DiffOp<'a, T>>,
 }
 
+/// `s` in green, uncolored without the `ansi` feature
+fn green(s: String) -> String {
+    #[cfg(feature = "ansi")]
+    let s = s.green().to_string();
+    s
+}
+
+/// `s` in red, uncolored without the `ansi` feature
+fn red(s: String) -> String {
+    #[cfg(feature = "ansi")]
+    let s = s.red().to_string();
+    s
+}
+
+/// `s` in yellow, uncolored without the `ansi` feature
+fn yellow(s: String) -> String {
+    #[cfg(feature = "ansi")]
+    let s = s.yellow().to_string();
+    s
+}
+
 impl<'a, T: fmt::Display> SliceChangeset<'a, T> {
     pub fn format(&self, skip_same: bool) -> String {
         let mut out: Vec<String> = Vec::with_capacity(self.diff.len());
@@ -69,13 +1291,13 @@ impl<'a, T: fmt::Display> SliceChangeset<'a, T> {
 
                 DiffOp::Insert(a) => {
                     for i in a.iter() {
-                        out.push((format!("+   {}", i).green()).to_string());
+                        out.push(green(format!("+   {}", i)));
                     }
                 }
 
                 DiffOp::Remove(a) => {
                     for i in a.iter() {
-                        out.push(format!("-   {}", i).red().to_string());
+                        out.push(red(format!("-   {}", i)));
                     }
                 }
                 DiffOp::Replace(a, b) => {
@@ -83,13 +1305,13 @@ impl<'a, T: fmt::Display> SliceChangeset<'a, T> {
                     let max_len = std::cmp::max(a.len(), b.len());
 
                     for i in 0..min_len {
-                        out.push(format!("~   {} -> {}", a[i], b[i]).yellow().to_string());
+                        out.push(yellow(format!("~   {} -> {}", a[i], b[i])));
                     }
                     for i in min_len..max_len {
                         if max_len == a.len() {
-                            out.push(format!("-   {}", a[i]).red().to_string());
+                            out.push(red(format!("-   {}", a[i])));
                         } else {
-                            out.push(format!("+   {}", b[i]).green().to_string());
+                            out.push(green(format!("+   {}", b[i])));
                         }
                     }
                 }
@@ -105,15 +1327,88 @@ impl<'a, T: fmt::Display> fmt::Display for SliceChangeset<'a, T> {
     }
 }
 
-/// Diff two arbitary slices with elements that support Display trait
-pub fn diff_slice<'a, T: PartialEq + std::fmt::Display>(
-    x: &'a [T],
-    y: &'a [T],
-) -> SliceChangeset<'a, T> {
+/// Diff two arbitary slices, [`SliceChangeset`] can be formatted if elements support Display trait
+///
+/// ```
+/// use prettydiff::basic::DiffOp;
+///
+/// #[derive(Debug, PartialEq)]
+/// enum Token {
+///     Ident(&'static str),
+///     Number(u32),
+/// }
+///
+/// let old = [Token::Ident("x"), Token::Number(1)];
+/// let new = [Token::Ident("x"), Token::Number(2)];
+/// let changeset = prettydiff::diff_slice(&old, &new);
+/// assert_eq!(changeset.diff[1], DiffOp::Replace(&old[1..], &new[1..]));
+/// ```
+pub fn diff_slice<'a, T: PartialEq>(x: &'a [T], y: &'a [T]) -> SliceChangeset<'a, T> {
     let diff = diff(x, y);
     SliceChangeset { diff }
 }
 
+/// Diffs raw bytes, for binary data which isn't valid UTF-8. See [`format_hex`] to display the result
+pub fn diff_bytes<'a>(x: &'a [u8], y: &'a [u8]) -> Vec<DiffOp<'a, u8>> {
+    diff(x, y)
+}
+
+/// Formats a byte diff as two hex columns with offsets, old bytes on the left and new
+/// ones on the right, 8 bytes per row. Removed bytes are red, inserted ones green.
+pub fn format_hex(ops: &[DiffOp<u8>]) -> String {
+    const WIDTH: usize = 8;
+    let hex = |bytes: &[u8]| {
+        bytes
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    let mut out = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    for op in ops {
+        let (old, new): (&[u8], &[u8]) = match op {
+            DiffOp::Equal(a) => (a, a),
+            DiffOp::Insert(b) => (&[], b),
+            DiffOp::Remove(a) => (a, &[]),
+            DiffOp::Replace(a, b) => (a, b),
+        };
+        let equal = matches!(op, DiffOp::Equal(_));
+        let rows = (max(old.len(), new.len()) + WIDTH - 1) / WIDTH;
+        for row in 0..rows {
+            let range = |len: usize| min(row * WIDTH, len)..min((row + 1) * WIDTH, len);
+            let (old_chunk, new_chunk) = (&old[range(old.len())], &new[range(new.len())]);
+            let offset = |chunk: &[u8], start: usize| {
+                if chunk.is_empty() {
+                    " ".repeat(8)
+                } else {
+                    format!("{:08x}", start + row * WIDTH)
+                }
+            };
+            let (old_offset, new_offset) = (offset(old_chunk, i), offset(new_chunk, j));
+            let mut old_hex = format!("{:<width$}", hex(old_chunk), width = WIDTH * 3 - 1);
+            let mut new_hex = hex(new_chunk);
+            if !equal && !old_chunk.is_empty() {
+                old_hex = red(old_hex);
+            }
+            if !equal && !new_chunk.is_empty() {
+                new_hex = green(new_hex);
+            }
+            out.push(
+                format!(
+                    "{}  {}  |  {}  {}",
+                    old_offset, old_hex, new_offset, new_hex
+                )
+                .trim_end()
+                .to_string(),
+            );
+        }
+        i += old.len();
+        j += new.len();
+    }
+    out.join("\n")
+}
+
 #[test]
 fn test_basic() {
     assert_eq!(
@@ -163,3 +1458,717 @@ fn test_basic() {
         diff_slice(&["a", "c", "d", "b"], &["a", "e", "b"])
     );
 }
+
+#[test]
+fn test_diff_trivial() {
+    let lines: Vec<u32> = (0..100_000).collect();
+    assert_eq!(diff(&lines, &lines), vec![DiffOp::Equal(&lines[..])]);
+    assert_eq!(
+        diff(&[1, 2], &[3, 4, 5]),
+        vec![DiffOp::Replace(&[1, 2], &[3, 4, 5])]
+    );
+    assert_eq!(diff(&[], &[1]), vec![DiffOp::Insert(&[1])]);
+    assert_eq!(diff(&[1], &[]), vec![DiffOp::Remove(&[1])]);
+    assert_eq!(diff::<u32>(&[], &[]), vec![]);
+    let (old, new): (Vec<u32>, Vec<u32>) = ((0..3000).collect(), (3000..5000).collect());
+    assert_eq!(diff(&old, &new), vec![DiffOp::Replace(&old[..], &new[..])]);
+    assert_eq!(
+        diff_by(&["a", "b"], &["A", "B"], |a, b| a == b),
+        vec![DiffOp::Replace(&["a", "b"][..], &["A", "B"][..])]
+    );
+}
+
+#[test]
+fn test_shift_changes() {
+    let x = ["a", "b", "c"];
+    let y = ["a", "b", "a", "b", "c"];
+    let shift = |direction| shift_changes(&x, &y, diff(&x, &y), direction);
+    assert_eq!(
+        shift(ShiftDirection::Up),
+        vec![DiffOp::Insert(&["a", "b"]), DiffOp::Equal(&["a", "b", "c"])]
+    );
+    assert_eq!(
+        shift(ShiftDirection::Down),
+        vec![
+            DiffOp::Equal(&["a", "b"]),
+            DiffOp::Insert(&["a", "b"]),
+            DiffOp::Equal(&["c"]),
+        ]
+    );
+    assert_eq!(shift(ShiftDirection::None), diff(&x, &y));
+    assert_eq!(
+        shift_changes(&y, &x, diff(&y, &x), ShiftDirection::Up),
+        vec![DiffOp::Remove(&["a", "b"]), DiffOp::Equal(&["a", "b", "c"])]
+    );
+    // Replace blocks stay in place
+    let x = [1, 2, 2, 3];
+    let y = [1, 2, 4, 3];
+    assert_eq!(
+        shift_changes(&x, &y, diff(&x, &y), ShiftDirection::Up),
+        diff(&x, &y)
+    );
+}
+
+#[test]
+fn test_diff_chunked() {
+    let x: Vec<u32> = (0..500).collect();
+    let mut y = x.clone();
+    y[10] = 1000;
+    y.remove(120);
+    y.insert(299, 2000);
+    y.splice(400..410, [3000, 3001]);
+    let sides = |ops: &[DiffOp<u32>]| {
+        let (mut old, mut new) = (Vec::new(), Vec::new());
+        for op in ops {
+            match op {
+                DiffOp::Equal(a) => {
+                    old.extend_from_slice(a);
+                    new.extend_from_slice(a);
+                }
+                DiffOp::Insert(b) => new.extend_from_slice(b),
+                DiffOp::Remove(a) => old.extend_from_slice(a),
+                DiffOp::Replace(a, b) => {
+                    old.extend_from_slice(a);
+                    new.extend_from_slice(b);
+                }
+            }
+        }
+        (old, new)
+    };
+    for window in [1, 7, 64, 1000] {
+        let chunked = diff_chunked(&x, &y, window);
+        assert_eq!(sides(&chunked), (x.clone(), y.clone()));
+        if window > 10 {
+            assert_eq!(chunked, diff(&x, &y));
+        }
+    }
+    assert_eq!(diff_chunked(&x, &[], 16), vec![DiffOp::Remove(&x[..])]);
+}
+
+#[test]
+fn test_hunk_metrics() {
+    let x: Vec<u32> = (0..20).collect();
+    let mut y = x.clone();
+    y[1] = 100;
+    y.splice(12..15, [200]);
+    let hunks = into_hunks(&diff(&x, &y), 1);
+    assert_eq!(hunks.len(), 2);
+    assert_eq!(
+        hunks[0],
+        Hunk {
+            old_start: 0,
+            old_len: 3,
+            new_start: 0,
+            new_len: 3,
+            ops: vec![
+                DiffOp::Equal(&[0]),
+                DiffOp::Replace(&[1], &[100]),
+                DiffOp::Equal(&[2]),
+            ],
+        }
+    );
+    assert_eq!((hunks[1].old_start, hunks[1].new_start), (11, 11));
+    assert_eq!(hunks[0].change_count(), 2);
+    assert_eq!(hunks[1].change_count(), 4);
+    assert!((hunks[0].similarity() - 2.0 / 3.0).abs() < 1e-9);
+    assert!(hunks[1].similarity() < hunks[0].similarity());
+    let mut by_size: Vec<_> = hunks.iter().map(Hunk::change_count).collect();
+    by_size.sort_unstable_by(|a, b| b.cmp(a));
+    assert_eq!(by_size, [4, 2]);
+}
+
+#[cfg(feature = "ansi")]
+#[test]
+fn test_colored_summary() {
+    let mut stats = DiffStats {
+        insertions: 3,
+        deletions: 1,
+        unchanged: 5,
+        moved: 0,
+        replacements: 0,
+    };
+    assert_eq!(
+        stats.colored_summary(),
+        format!("{} {}", "+3".green(), "-1".red())
+    );
+    stats.moved = 2;
+    assert_eq!(
+        stats.colored_summary(),
+        format!("{} {}, 2 moved", "+3".green(), "-1".red())
+    );
+    stats.replacements = 1;
+    assert_eq!(
+        stats.colored_summary(),
+        format!("{} {}, 1 modified, 2 moved", "+3".green(), "-1".red())
+    );
+    assert_eq!(
+        stats.colored_summary(),
+        "\x1b[32m+3\x1b[39m \x1b[31m-1\x1b[39m, 1 modified, 2 moved"
+    );
+    assert_eq!(
+        stats.colored_summary_with(2, 1),
+        "2 files changed, 1 hunk, \x1b[32m+3\x1b[39m \x1b[31m-1\x1b[39m, 1 modified, 2 moved"
+    );
+    stats.moved = 0;
+    assert_eq!(
+        stats.colored_summary_with(1, 3),
+        "1 file changed, 3 hunks, \x1b[32m+3\x1b[39m \x1b[31m-1\x1b[39m, 1 modified"
+    );
+}
+
+#[test]
+fn test_stats_categories() {
+    let ops = [
+        DiffOp::Equal(&["a"][..]),
+        DiffOp::Insert(&["b", "c"][..]),
+        DiffOp::Equal(&["d"][..]),
+        DiffOp::Remove(&["e"][..]),
+        DiffOp::Replace(&["f", "g", "h"][..], &["F", "G"][..]),
+        DiffOp::Remove(&["m1", "m2"][..]),
+        DiffOp::Equal(&["i"][..]),
+        DiffOp::Insert(&["m1", "m2"][..]),
+    ];
+    let counts = stats(&ops);
+    assert_eq!(
+        counts,
+        DiffStats {
+            insertions: 4,
+            deletions: 4,
+            unchanged: 3,
+            moved: 2,
+            replacements: 1,
+        }
+    );
+    assert_eq!(counts.to_string(), "+4 -4 →2");
+    assert_eq!(
+        stats(&[DiffOp::Insert(&[1][..]), DiffOp::Remove(&[2][..])]).to_string(),
+        "+1 -1"
+    );
+}
+
+#[test]
+fn test_owned_diff_op() {
+    let (x, y) = ([1, 2, 3], [1, 4, 3, 5]);
+    let ops = diff(&x, &y);
+    let owned: Vec<OwnedDiffOp<i32>> = ops.iter().map(|op| op.into_owned()).collect();
+    drop(ops);
+    assert_eq!(
+        owned,
+        vec![
+            OwnedDiffOp::Equal(vec![1]),
+            OwnedDiffOp::Replace(vec![2], vec![4]),
+            OwnedDiffOp::Equal(vec![3]),
+            OwnedDiffOp::Insert(vec![5]),
+        ]
+    );
+    let borrowed: Vec<DiffOp<i32>> = owned.iter().map(|op| op.as_diff_op()).collect();
+    assert_eq!(borrowed, diff(&x, &y));
+
+    let (old, new) = (vec!["a", "b"], vec!["a", "c"]);
+    let strings: Vec<OwnedDiffOp<String>> = diff(&old, &new)
+        .into_iter()
+        .map(|op| op.map(|s| s.to_string()))
+        .collect();
+    assert_eq!(
+        strings[1],
+        OwnedDiffOp::Replace(vec!["b".into()], vec!["c".into()])
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde_diff_op() {
+    let (old, new) = (vec!["a", "b", "c"], vec!["a", "x", "c", "d"]);
+    let ops = diff(&old, &new);
+    let json = serde_json::to_string(&ops).unwrap();
+    assert_eq!(
+        json,
+        r#"[{"op":"equal","items":["a"]},{"op":"replace","old":["b"],"new":["x"]},{"op":"equal","items":["c"]},{"op":"insert","new":["d"]}]"#
+    );
+    let owned: Vec<OwnedDiffOp<String>> = serde_json::from_str(&json).unwrap();
+    assert_eq!(
+        owned,
+        ops.iter()
+            .map(|op| op.map(|s| s.to_string()))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(serde_json::to_string(&owned).unwrap(), json);
+
+    let removed: OwnedDiffOp<i32> = serde_json::from_str(r#"{"op":"remove","old":[1,2]}"#).unwrap();
+    assert_eq!(removed, OwnedDiffOp::Remove(vec![1, 2]));
+    assert!(serde_json::from_str::<OwnedDiffOp<i32>>(r#"{"op":"move","old":[1]}"#).is_err());
+}
+
+#[test]
+fn test_diff_with_limit() {
+    let x: Vec<u32> = (0..200_000).collect();
+    let y: Vec<u32> = (200_000..400_000).collect();
+    assert_eq!(diff_with_limit(&x, &y, 100), None);
+    let mut z = x.clone();
+    z[1000] = 0;
+    z.push(1);
+    assert_eq!(
+        diff_with_limit(&x, &z, 3),
+        Some(vec![
+            DiffOp::Equal(&x[..1000]),
+            DiffOp::Replace(&x[1000..1001], &z[1000..1001]),
+            DiffOp::Equal(&x[1001..]),
+            DiffOp::Insert(&z[200_000..]),
+        ])
+    );
+    assert_eq!(diff_with_limit(&x, &z, 2), None);
+    assert_eq!(diff_with_limit::<u32>(&[], &[], 0), Some(vec![]));
+}
+
+#[test]
+fn test_diff_myers() {
+    assert_eq!(diff_myers::<i32>(&[], &[]), vec![]);
+    assert_eq!(
+        diff_myers(&[1, 2, 3], &[1, 4, 3, 5]),
+        vec![
+            DiffOp::Equal(&[1]),
+            DiffOp::Replace(&[2], &[4]),
+            DiffOp::Equal(&[3]),
+            DiffOp::Insert(&[5]),
+        ]
+    );
+    assert_eq!(diff_myers(&[1, 2], &[]), vec![DiffOp::Remove(&[1, 2])]);
+
+    // Both algorithms find a smallest diff, so they must agree on the number of equal elements
+    let mut seed = 7u32;
+    let mut random = |len: usize| -> Vec<u32> {
+        (0..len)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (seed >> 16) % 4
+            })
+            .collect()
+    };
+    for len in 0..30 {
+        let (x, y) = (random(len), random(len / 2 + 3));
+        let equal = |ops: &[DiffOp<u32>]| -> usize {
+            ops.iter()
+                .map(|op| match op {
+                    DiffOp::Equal(a) => a.len(),
+                    _ => 0,
+                })
+                .sum()
+        };
+        let myers = diff_myers(&x, &y);
+        assert_eq!(equal(&myers), equal(&diff(&x, &y)));
+        let (mut old, mut new) = (Vec::new(), Vec::new());
+        for op in &myers {
+            match op {
+                DiffOp::Equal(a) => {
+                    old.extend_from_slice(a);
+                    new.extend_from_slice(a);
+                }
+                DiffOp::Insert(b) => new.extend_from_slice(b),
+                DiffOp::Remove(a) => old.extend_from_slice(a),
+                DiffOp::Replace(a, b) => {
+                    old.extend_from_slice(a);
+                    new.extend_from_slice(b);
+                }
+            }
+        }
+        assert_eq!((old, new), (x, y));
+    }
+}
+
+#[test]
+fn test_diff_patience() {
+    let old = [
+        "fn removed() {",
+        "    return 1;",
+        "    old();",
+        "}",
+        "",
+        "fn kept() {",
+        "    kept();",
+        "    return 1;",
+        "}",
+        "",
+    ];
+    let new = [
+        "fn kept() {",
+        "    kept();",
+        "    return 1;",
+        "}",
+        "",
+        "fn added() {",
+        "    return 1;",
+        "    new();",
+        "    return 1;",
+        "}",
+        "",
+    ];
+    let unchanged = |ops: &[DiffOp<&str>], line: &str| {
+        ops.iter()
+            .any(|op| matches!(op, DiffOp::Equal(a) if a.contains(&line)))
+    };
+
+    // LCS matches `return 1;` and braces across functions and splits `kept` apart
+    let lcs = diff(&old, &new);
+    assert!(!unchanged(&lcs, "fn kept() {"));
+
+    assert_eq!(
+        diff_patience(&old, &new),
+        vec![
+            DiffOp::Remove(&old[..5]),
+            DiffOp::Equal(&old[5..7]),
+            DiffOp::Insert(&new[2..8]),
+            DiffOp::Equal(&old[7..]),
+        ]
+    );
+    assert_eq!(
+        diff_patience(&[1, 2, 3], &[1, 2, 3]),
+        vec![DiffOp::Equal(&[1, 2, 3])]
+    );
+    assert_eq!(diff_patience::<i32>(&[], &[1]), vec![DiffOp::Insert(&[1])]);
+    // Unique elements win over longer runs of repeated ones
+    assert_eq!(
+        diff_patience(&[1, 1, 2], &[2, 1, 1]),
+        vec![
+            DiffOp::Remove(&[1, 1]),
+            DiffOp::Equal(&[2]),
+            DiffOp::Insert(&[1, 1])
+        ]
+    );
+}
+
+#[test]
+fn test_ratio() {
+    assert_eq!(ratio::<i32>(&[], &[]), 1.0);
+    assert_eq!(ratio(&[1, 2], &[]), 0.0);
+    assert_eq!(ratio(&[], &[1, 2]), 0.0);
+    assert_eq!(ratio(&[1, 2, 3], &[1, 2, 3]), 1.0);
+    assert_eq!(ratio(&[1, 2, 3, 4], &[1, 5, 3, 6]), 0.5);
+    assert_eq!(ratio(&[1, 2], &[3]), 0.0);
+    let (x, y) = ([1, 2, 3, 4, 5, 6], [0, 2, 4, 5, 7]);
+    assert_eq!(ratio(&x, &y), ops_ratio(&diff(&x, &y)));
+}
+
+#[test]
+fn test_diff_by() {
+    let old = ["Foo  ", "bar"];
+    let new = ["foo", "bar"];
+    assert_eq!(
+        diff(&old, &new),
+        vec![
+            DiffOp::Replace(&old[..1], &new[..1]),
+            DiffOp::Equal(&old[1..])
+        ]
+    );
+    let loose = diff_by(&old, &new, |a, b| {
+        a.trim_end().eq_ignore_ascii_case(b.trim_end())
+    });
+    assert_eq!(loose, vec![DiffOp::Equal(&old[..])]);
+}
+
+#[test]
+fn test_edit_distance() {
+    assert_eq!(edit_distance(&[1, 2, 3], &[1, 2, 3]), 0);
+    assert_eq!(edit_distance(&[1, 2, 3], &[4, 5]), 5);
+    assert_eq!(edit_distance::<i32>(&[], &[]), 0);
+    let chars = |s: &str| s.chars().collect::<Vec<_>>();
+    // k -> s, e -> i and an inserted g
+    assert_eq!(edit_distance(&chars("kitten"), &chars("sitting")), 5);
+}
+
+#[test]
+fn test_apply() {
+    let old: Vec<&str> = "a b c d e".split(' ').collect();
+    let new: Vec<&str> = "a x c e f".split(' ').collect();
+    let ops = diff(&old, &new);
+    assert_eq!(apply(&old, &ops), Ok(new.clone()));
+    assert_eq!(
+        apply(&old, &[]),
+        Err(ApplyError::Unconsumed { position: 0 })
+    );
+    assert_eq!(
+        apply(&old[..4], &ops),
+        Err(ApplyError::Mismatch { position: 4 })
+    );
+    let other: Vec<&str> = "a b z d e".split(' ').collect();
+    assert_eq!(
+        apply(&other, &ops),
+        Err(ApplyError::Mismatch { position: 2 })
+    );
+    assert_eq!(
+        ApplyError::Mismatch { position: 2 }.to_string(),
+        "diff doesn't match old elements at 2"
+    );
+}
+
+#[test]
+fn test_reverse() {
+    let old = [1, 2, 3, 4, 5];
+    let new = [1, 6, 3, 5, 7];
+    let ops = diff(&old, &new);
+    assert_eq!(reverse(reverse(ops.clone())), ops);
+    assert_eq!(apply(&new, &reverse(ops)), Ok(old.to_vec()));
+    assert_eq!(
+        reverse(vec![
+            DiffOp::Insert(&[1][..]),
+            DiffOp::Replace(&[2][..], &[3][..])
+        ]),
+        vec![
+            DiffOp::Remove(&[1][..]),
+            DiffOp::Replace(&[3][..], &[2][..])
+        ]
+    );
+}
+
+#[test]
+fn test_split_replaces() {
+    let old = [1, 2, 3, 4, 5];
+    let new = [1, 6, 3, 5, 7];
+    let ops = split_replaces(diff(&old, &new));
+    assert!(!ops.iter().any(|op| matches!(op, DiffOp::Replace(..))));
+    assert_eq!(
+        ops[..3],
+        [
+            DiffOp::Equal(&[1][..]),
+            DiffOp::Remove(&[2][..]),
+            DiffOp::Insert(&[6][..])
+        ]
+    );
+    assert_eq!(apply(&old, &ops), Ok(new.to_vec()));
+}
+
+#[test]
+fn test_merge3() {
+    let lines = |s: &'static str| s.split(' ').collect::<Vec<_>>();
+    let base = lines("a b c d e f");
+    assert_eq!(
+        merge3(&base, &lines("a B c d e f"), &lines("a b c d E f g")),
+        MergeResult::Clean(lines("a B c d E f g"))
+    );
+    assert_eq!(
+        merge3(&base, &lines("a B c d e f"), &lines("a B c d e f")),
+        MergeResult::Clean(lines("a B c d e f"))
+    );
+    assert_eq!(
+        merge3(&base, &base, &base),
+        MergeResult::Clean(base.clone())
+    );
+    assert_eq!(
+        merge3(&base, &lines("a b X d e f"), &lines("a b Y Z d e")),
+        MergeResult::Conflicts(vec![
+            MergeChunk::Resolved(lines("a b")),
+            MergeChunk::Conflict {
+                base: lines("c"),
+                left: lines("X"),
+                right: lines("Y Z"),
+            },
+            MergeChunk::Resolved(lines("d e")),
+        ])
+    );
+}
+
+#[test]
+fn test_diff_iter() {
+    let (x, y) = ([1, 2, 3, 4, 5, 6], [0, 1, 3, 4, 7, 6, 8]);
+    assert_eq!(diff_iter(&x, &y).collect::<Vec<_>>(), diff(&x, &y));
+    let first_change = diff_iter(&x, &y).find(|op| !matches!(op, DiffOp::Equal(_)));
+    assert_eq!(first_change, Some(DiffOp::Insert(&[0][..])));
+    assert_eq!(diff_iter::<i32>(&[], &[]).next(), None);
+}
+
+#[test]
+fn test_diff_large() {
+    // 50k elements, above the threshold of the full LCS table
+    let x: Vec<u32> = (0..50_000).map(|i| i % 1000).collect();
+    let mut y = x.clone();
+    y.splice(24_000..24_500, (0..300).map(|i| i % 7));
+    y.insert(25_000, 5);
+    y.remove(25_500);
+    let ops = diff(&x, &y);
+    assert_eq!(apply(&x, &ops), Ok(y.clone()));
+    let equal: usize = ops
+        .iter()
+        .map(|op| match op {
+            DiffOp::Equal(a) => a.len(),
+            _ => 0,
+        })
+        .sum();
+    // Everything outside of the edited region is unchanged
+    let (start, end) = (23_900, 25_600);
+    let core = lcs::Table::new(&x[start..end], &y[start..end - 200]).longest_len();
+    assert_eq!(equal, x.len() - (end - start) + core);
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn test_diff_parallel() {
+    let old: Vec<String> = (0..2_000).map(|i| format!("line {}", i)).collect();
+    let mut new = old.clone();
+    for i in (0..new.len()).step_by(150) {
+        new[i] = format!("changed {}", i);
+    }
+    new.insert(1_000, "inserted".to_string());
+    new.remove(1_500);
+    assert_eq!(
+        diff_anchored(&old, &new, 0),
+        diff_anchored(&old, &new, usize::MAX)
+    );
+    // Every changed line is between two unique ones here, so anchors don't change the result
+    assert_eq!(diff_parallel(&old, &new), diff(&old, &new));
+
+    let (x, y) = (["a", "x", "b", "x"], ["b", "x", "a", "x", "c"]);
+    assert_eq!(apply(&x, &diff_parallel(&x, &y)), Ok(y.to_vec()));
+    assert_eq!(diff_parallel::<u8>(&[], &[]), vec![]);
+
+    // Anchoring on the moved `u` gives up the three `a`s which `diff` keeps
+    let (x, y) = (["u", "a", "a", "a"], ["a", "a", "a", "u"]);
+    assert_eq!(
+        diff_parallel(&x, &y),
+        vec![
+            DiffOp::Insert(&y[..3]),
+            DiffOp::Equal(&x[..1]),
+            DiffOp::Remove(&x[1..]),
+        ]
+    );
+    assert_eq!(
+        diff(&x, &y),
+        vec![
+            DiffOp::Remove(&x[..1]),
+            DiffOp::Equal(&x[1..]),
+            DiffOp::Insert(&y[3..]),
+        ]
+    );
+}
+
+#[cfg(feature = "ansi")]
+#[test]
+fn test_diff_bytes() {
+    let old = [0x08, 0x96, 0x01, 0x12, 0x03, b'a', b'b', b'c', 0x18, 0x01];
+    let new = [
+        0x08, 0x96, 0x01, 0x12, 0x03, b'a', b'x', b'c', 0x18, 0x01, 0x20,
+    ];
+    let ops = diff_bytes(&old, &new);
+    assert_eq!(ops[1], DiffOp::Replace(&[b'b'][..], &[b'x'][..]));
+    assert_eq!(
+        format_hex(&ops),
+        format!(
+            "00000000  {:<23}  |  00000000  08 96 01 12 03 61\n\
+             00000006  {}  |  00000006  {}\n\
+             00000007  {:<23}  |  00000007  63 18 01\n\
+             {:<33}  |  0000000a  {}",
+            "08 96 01 12 03 61",
+            format!("{:<23}", "62").red(),
+            "78".green(),
+            "63 18 01",
+            "",
+            "20".green()
+        )
+    );
+}
+
+#[test]
+fn test_detect_moves() {
+    // `fn a` moved from the top to the bottom
+    let old = [
+        "fn a() {",
+        "    1",
+        "}",
+        "fn main() {",
+        "    a();",
+        "    a();",
+        "}",
+    ];
+    let new = [
+        "fn main() {",
+        "    a();",
+        "    a();",
+        "}",
+        "fn a() {",
+        "    1",
+        "}",
+    ];
+    let ops = diff(&old, &new);
+    let moves = detect_moves(&ops);
+    assert_eq!(moves.len(), 1);
+    let (remove, insert) = moves[0];
+    assert_eq!(ops[remove], DiffOp::Remove(&old[..3]));
+    assert_eq!(ops[insert], DiffOp::Insert(&new[4..]));
+    assert_eq!(detect_moves(&diff(&[1, 2], &[1, 3])), vec![]);
+}
+
+#[test]
+fn test_diff_indexed() {
+    let (x, y) = ([1, 2, 3, 4, 5], [0, 2, 3, 5, 6]);
+    let indexed = diff_indexed(&x, &y);
+    assert_eq!(
+        indexed.iter().map(|(_, _, op)| *op).collect::<Vec<_>>(),
+        diff(&x, &y)
+    );
+    // Ranges tile both inputs without gaps or overlaps
+    let (mut i, mut j) = (0, 0);
+    for (old, new, op) in &indexed {
+        assert_eq!((old.start, new.start), (i, j));
+        match op {
+            DiffOp::Equal(a) => assert_eq!(&x[old.clone()], *a),
+            DiffOp::Insert(b) => assert!(old.is_empty() && &y[new.clone()] == *b),
+            DiffOp::Remove(a) => assert!(new.is_empty() && &x[old.clone()] == *a),
+            DiffOp::Replace(a, b) => assert!(&x[old.clone()] == *a && &y[new.clone()] == *b),
+        }
+        i = old.end;
+        j = new.end;
+    }
+    assert_eq!((i, j), (x.len(), y.len()));
+}
+
+#[test]
+fn test_diff_op_helpers() {
+    let ops = diff(&["a", "b", "c"], &["a", "x", "y", "c", "d"]);
+    assert_eq!(
+        ops.iter().map(|op| op.tag()).collect::<Vec<_>>(),
+        ["equal", "replace", "equal", "insert"]
+    );
+    assert_eq!((ops[1].old_len(), ops[1].new_len()), (1, 2));
+    assert_eq!((ops[3].old_len(), ops[3].new_len()), (0, 1));
+    assert!(ops[0].is_equal() && ops[1].is_change());
+    assert_eq!(ops[1].to_string(), "-b\n+x\n+y");
+    assert_eq!(ops[0].to_string(), " a");
+    assert_eq!(DiffOp::Remove(&[1, 2]).to_string(), "-1\n-2");
+}
+
+#[test]
+fn test_into_hunks_merge() {
+    let x: Vec<u32> = (0..20).collect();
+    // Changes at 3 and 8 are 4 = 2 * context equal elements apart
+    let mut y = x.clone();
+    y[3] = 100;
+    y[8] = 200;
+    let hunks = into_hunks(&diff(&x, &y), 2);
+    assert_eq!(hunks.len(), 1);
+    assert_eq!((hunks[0].old_start, hunks[0].old_len), (1, 10));
+    assert_eq!(hunks[0].ops.len(), 5);
+    // One more equal element splits them
+    let mut y = x.clone();
+    y[3] = 100;
+    y[9] = 200;
+    let hunks = into_hunks(&diff(&x, &y), 2);
+    assert_eq!(hunks.len(), 2);
+    assert_eq!((hunks[0].old_start, hunks[0].old_len), (1, 5));
+    assert_eq!((hunks[1].old_start, hunks[1].old_len), (7, 5));
+}
+
+#[test]
+fn test_into_hunks_bounds() {
+    let x: Vec<u32> = (0..10).collect();
+    let mut y = x.clone();
+    y[0] = 100;
+    y[9] = 200;
+    let hunks = into_hunks(&diff(&x, &y), 3);
+    assert_eq!(hunks.len(), 2);
+    assert_eq!((hunks[0].old_start, hunks[0].old_len), (0, 4));
+    assert_eq!((hunks[0].new_start, hunks[0].new_len), (0, 4));
+    assert_eq!((hunks[1].old_start, hunks[1].old_len), (6, 4));
+    assert_eq!((hunks[1].new_start, hunks[1].new_len), (6, 4));
+    // Insertion before the first element
+    let hunks = into_hunks(&diff(&[1, 2], &[0, 1, 2]), 3);
+    assert_eq!((hunks[0].old_start, hunks[0].old_len), (0, 2));
+    assert_eq!((hunks[0].new_start, hunks[0].new_len), (0, 3));
+}
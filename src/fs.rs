@@ -0,0 +1,340 @@
+//! Recursive diff of two directories, enabled by the `fs` feature.
+
+use crate::text::{diff_lines, ChangeKind, LineChangeset};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Text file which differs between two directories
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileDiff {
+    /// Path relative to the compared directories
+    pub path: PathBuf,
+    pub old: String,
+    pub new: String,
+}
+
+impl FileDiff {
+    pub fn changeset(&self) -> LineChangeset<'_> {
+        diff_lines(&self.old, &self.new)
+    }
+}
+
+/// Result of [`diff_dirs`], all paths are relative to the compared directories
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DirDiff {
+    pub added: Vec<PathBuf>,
+    pub removed: Vec<PathBuf>,
+    /// Removed files with the same content as an added file, as `(old path, new path)`
+    pub renamed: Vec<(PathBuf, PathBuf)>,
+    pub modified: Vec<FileDiff>,
+    /// Files which differ but are not valid UTF-8
+    pub binary: Vec<PathBuf>,
+    /// Files with the same content in both directories
+    pub unchanged: Vec<PathBuf>,
+    /// Entries which could not be read, with the error message
+    pub errors: Vec<(PathBuf, String)>,
+}
+
+impl DirDiff {
+    /// Every changed file sorted by path, renamed files by their new path. Modified text and
+    /// binary files are [`ChangeKind::Modify`]
+    pub fn classify(&self) -> Vec<(PathBuf, ChangeKind)> {
+        let mut changes: Vec<(PathBuf, ChangeKind)> = Vec::new();
+        changes.extend(
+            self.added
+                .iter()
+                .map(|path| (path.clone(), ChangeKind::Add)),
+        );
+        changes.extend(
+            self.removed
+                .iter()
+                .map(|path| (path.clone(), ChangeKind::Remove)),
+        );
+        changes.extend(
+            self.renamed
+                .iter()
+                .map(|(_, path)| (path.clone(), ChangeKind::Rename)),
+        );
+        changes.extend(
+            self.modified
+                .iter()
+                .map(|file| (file.path.clone(), ChangeKind::Modify)),
+        );
+        changes.extend(
+            self.binary
+                .iter()
+                .map(|path| (path.clone(), ChangeKind::Modify)),
+        );
+        changes.sort_by(|a, b| a.0.cmp(&b.0));
+        changes
+    }
+
+    /// One line per changed file sorted by path: `A path` for added files, `D path` for removed
+    /// ones, `R old -> new` for renamed ones sorted by the new path and `M path (binary)` for
+    /// modified binary files. Modified text files are followed by their
+    /// [`DiffStats`](crate::basic::DiffStats), e.g. `M path +1 -1` for one changed line or
+    /// `M path +2 -0 →3` with moved lines
+    pub fn summary(&self) -> String {
+        let mut lines: Vec<(&Path, String)> = Vec::new();
+        lines.extend(
+            self.added
+                .iter()
+                .map(|path| (path.as_path(), format!("A {}", path.display()))),
+        );
+        lines.extend(
+            self.removed
+                .iter()
+                .map(|path| (path.as_path(), format!("D {}", path.display()))),
+        );
+        lines.extend(self.renamed.iter().map(|(old, new)| {
+            let line = format!("R {} -> {}", old.display(), new.display());
+            (new.as_path(), line)
+        }));
+        lines.extend(self.modified.iter().map(|file| {
+            let line = format!("M {} {}", file.path.display(), file.changeset().stats());
+            (file.path.as_path(), line)
+        }));
+        lines.extend(
+            self.binary
+                .iter()
+                .map(|path| (path.as_path(), format!("M {} (binary)", path.display()))),
+        );
+        lines.sort_by(|a, b| a.0.cmp(b.0));
+        lines.into_iter().map(|(_, line)| line + "\n").collect()
+    }
+}
+
+enum DirEntry {
+    File(PathBuf),
+    Link(PathBuf),
+}
+
+impl DirEntry {
+    fn read(&self) -> std::io::Result<Vec<u8>> {
+        match self {
+            DirEntry::File(path) => std::fs::read(path),
+            DirEntry::Link(path) => Ok(std::fs::read_link(path)?
+                .to_string_lossy()
+                .into_owned()
+                .into_bytes()),
+        }
+    }
+}
+
+fn walk_dir(
+    root: &Path,
+    relative: &Path,
+    entries: &mut BTreeMap<PathBuf, DirEntry>,
+    errors: &mut Vec<(PathBuf, String)>,
+) {
+    let dir = match std::fs::read_dir(root.join(relative)) {
+        Ok(dir) => dir,
+        Err(e) => return errors.push((relative.to_path_buf(), e.to_string())),
+    };
+    for entry in dir {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                errors.push((relative.to_path_buf(), e.to_string()));
+                continue;
+            }
+        };
+        let path = relative.join(entry.file_name());
+        match entry.file_type() {
+            Ok(kind) if kind.is_dir() => walk_dir(root, &path, entries, errors),
+            Ok(kind) if kind.is_symlink() => {
+                entries.insert(path, DirEntry::Link(entry.path()));
+            }
+            Ok(_) => {
+                entries.insert(path, DirEntry::File(entry.path()));
+            }
+            Err(e) => errors.push((path, e.to_string())),
+        }
+    }
+}
+
+/// Recursively compare two directories.
+///
+/// Symlinks are not followed, they are compared by their target path.
+/// Unreadable entries are collected into [`DirDiff::errors`].
+pub fn diff_dirs(old: &Path, new: &Path) -> DirDiff {
+    let mut result = DirDiff::default();
+    let mut old_entries = BTreeMap::new();
+    let mut new_entries = BTreeMap::new();
+    walk_dir(old, Path::new(""), &mut old_entries, &mut result.errors);
+    walk_dir(new, Path::new(""), &mut new_entries, &mut result.errors);
+
+    for (path, old_entry) in &old_entries {
+        let new_entry = match new_entries.get(path) {
+            Some(entry) => entry,
+            None => {
+                result.removed.push(path.clone());
+                continue;
+            }
+        };
+        let (old_data, new_data) = match (old_entry.read(), new_entry.read()) {
+            (Ok(old_data), Ok(new_data)) => (old_data, new_data),
+            (Err(e), _) | (_, Err(e)) => {
+                result.errors.push((path.clone(), e.to_string()));
+                continue;
+            }
+        };
+        if old_data == new_data {
+            result.unchanged.push(path.clone());
+            continue;
+        }
+        match (String::from_utf8(old_data), String::from_utf8(new_data)) {
+            (Ok(old), Ok(new)) => result.modified.push(FileDiff {
+                path: path.clone(),
+                old,
+                new,
+            }),
+            _ => result.binary.push(path.clone()),
+        }
+    }
+    result.added = new_entries
+        .keys()
+        .filter(|path| !old_entries.contains_key(*path))
+        .cloned()
+        .collect();
+    if !result.removed.is_empty() && !result.added.is_empty() {
+        find_renames(&mut result, &old_entries, &new_entries);
+    }
+    result
+}
+
+/// Moves removed files with the same content as an added file into [`DirDiff::renamed`].
+/// Empty and unreadable files are never renamed.
+fn find_renames(
+    result: &mut DirDiff,
+    old_entries: &BTreeMap<PathBuf, DirEntry>,
+    new_entries: &BTreeMap<PathBuf, DirEntry>,
+) {
+    let content = |entry: &DirEntry| entry.read().ok().filter(|data| !data.is_empty());
+    let mut added: Vec<(PathBuf, Option<Vec<u8>>)> = std::mem::take(&mut result.added)
+        .into_iter()
+        .map(|path| {
+            let data = content(&new_entries[&path]);
+            (path, data)
+        })
+        .collect();
+    for path in std::mem::take(&mut result.removed) {
+        let found = content(&old_entries[&path]).and_then(|data| {
+            added
+                .iter()
+                .position(|(_, added)| added.as_ref() == Some(&data))
+        });
+        match found {
+            Some(index) => result.renamed.push((path, added.remove(index).0)),
+            None => result.removed.push(path),
+        }
+    }
+    result.added = added.into_iter().map(|(path, _)| path).collect();
+}
+
+#[test]
+fn test_diff_dirs() {
+    let root = std::env::temp_dir().join(format!("prettydiff-dirs-{}", std::process::id()));
+    let (old, new) = (root.join("old"), root.join("new"));
+    std::fs::create_dir_all(old.join("sub")).unwrap();
+    std::fs::create_dir_all(new.join("sub")).unwrap();
+    let write = |dir: &Path, name: &str, data: &[u8]| std::fs::write(dir.join(name), data).unwrap();
+    write(&old, "same.txt", b"a\nb\n");
+    write(&new, "same.txt", b"a\nb\n");
+    write(&old, "sub/changed.txt", b"a\nb\n");
+    write(&new, "sub/changed.txt", b"a\nc\n");
+    write(&old, "removed.txt", b"x\n");
+    write(&new, "sub/added.txt", b"y\n");
+    write(&old, "data.bin", &[0xff, 0x00]);
+    write(&new, "data.bin", &[0xff, 0x01]);
+
+    let result = diff_dirs(&old, &new);
+    std::fs::remove_dir_all(&root).unwrap();
+
+    assert_eq!(result.added, vec![Path::new("sub").join("added.txt")]);
+    assert_eq!(result.removed, vec![PathBuf::from("removed.txt")]);
+    assert_eq!(result.binary, vec![PathBuf::from("data.bin")]);
+    assert_eq!(result.unchanged, vec![PathBuf::from("same.txt")]);
+    assert!(result.errors.is_empty());
+    assert_eq!(result.modified.len(), 1);
+    assert_eq!(
+        result.modified[0].path,
+        Path::new("sub").join("changed.txt")
+    );
+    assert_eq!(result.modified[0].changeset().stats().insertions, 1);
+    let changed = Path::new("sub").join("changed.txt");
+    let added = Path::new("sub").join("added.txt");
+    assert_eq!(
+        result.summary(),
+        format!(
+            "M data.bin (binary)\nD removed.txt\nA {}\nM {} +1 -1\n",
+            added.display(),
+            changed.display()
+        )
+    );
+
+    assert_eq!(
+        result.classify(),
+        vec![
+            (PathBuf::from("data.bin"), ChangeKind::Modify),
+            (PathBuf::from("removed.txt"), ChangeKind::Remove),
+            (added.clone(), ChangeKind::Add),
+            (changed.clone(), ChangeKind::Modify),
+        ]
+    );
+
+    let missing = diff_dirs(&root.join("missing"), &root.join("missing"));
+    assert_eq!(missing.errors.len(), 2);
+}
+
+#[test]
+fn test_diff_dirs_renamed() {
+    let root = std::env::temp_dir().join(format!("prettydiff-renames-{}", std::process::id()));
+    let (old, new) = (root.join("old"), root.join("new"));
+    std::fs::create_dir_all(&old).unwrap();
+    std::fs::create_dir_all(new.join("sub")).unwrap();
+    let write = |dir: &Path, name: &str, data: &[u8]| std::fs::write(dir.join(name), data).unwrap();
+    write(&old, "moved.txt", b"a\nb\n");
+    write(&new, "sub/moved.txt", b"a\nb\n");
+    write(&old, "edited.txt", b"x\n");
+    write(&new, "edited-too.txt", b"y\n");
+    write(&old, "empty.txt", b"");
+    write(&new, "also-empty.txt", b"");
+
+    let result = diff_dirs(&old, &new);
+    std::fs::remove_dir_all(&root).unwrap();
+
+    let moved = Path::new("sub").join("moved.txt");
+    assert_eq!(
+        result.renamed,
+        vec![(PathBuf::from("moved.txt"), moved.clone())]
+    );
+    assert_eq!(
+        result.removed,
+        vec![PathBuf::from("edited.txt"), PathBuf::from("empty.txt")]
+    );
+    assert_eq!(
+        result.added,
+        vec![
+            PathBuf::from("also-empty.txt"),
+            PathBuf::from("edited-too.txt")
+        ]
+    );
+    assert_eq!(
+        result.classify(),
+        vec![
+            (PathBuf::from("also-empty.txt"), ChangeKind::Add),
+            (PathBuf::from("edited-too.txt"), ChangeKind::Add),
+            (PathBuf::from("edited.txt"), ChangeKind::Remove),
+            (PathBuf::from("empty.txt"), ChangeKind::Remove),
+            (moved.clone(), ChangeKind::Rename),
+        ]
+    );
+    assert_eq!(
+        result.summary(),
+        format!(
+            "A also-empty.txt\nA edited-too.txt\nD edited.txt\nD empty.txt\nR moved.txt -> {}\n",
+            moved.display()
+        )
+    );
+}
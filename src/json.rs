@@ -0,0 +1,116 @@
+//! Semantic diff of JSON documents, enabled by the `json` feature.
+//!
+//! Both documents are parsed with `serde_json`, objects get sorted keys and everything is
+//! pretty-printed the same way, so only changed values show up in the line diff.
+
+use crate::text::{diff_lines, LineChangeset};
+use std::fmt;
+
+/// Canonical (sorted keys, pretty-printed) forms of two JSON documents, see [`diff_json`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonDiff {
+    pub old: String,
+    pub new: String,
+}
+
+impl JsonDiff {
+    pub fn changeset(&self) -> LineChangeset<'_> {
+        diff_lines(&self.old, &self.new)
+    }
+}
+
+/// Error of [`diff_json`], lines and columns start at 1
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JsonError {
+    /// Old document isn't valid JSON
+    Old { line: usize, column: usize },
+    /// New document isn't valid JSON
+    New { line: usize, column: usize },
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JsonError::Old { line, column } => write!(
+                formatter,
+                "invalid JSON in old document at {}:{}",
+                line, column
+            ),
+            JsonError::New { line, column } => write!(
+                formatter,
+                "invalid JSON in new document at {}:{}",
+                line, column
+            ),
+        }
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+/// Diffs two JSON documents ignoring formatting and key order. Numbers are compared by value,
+/// so `1.0` equals `1.00` but not `1`. Documents nested deeper than `serde_json` parses
+/// (128 levels) are rejected like invalid JSON.
+pub fn diff_json(old: &str, new: &str) -> Result<JsonDiff, JsonError> {
+    let old = canonicalize(old).map_err(|e| JsonError::Old {
+        line: e.line(),
+        column: e.column(),
+    })?;
+    let new = canonicalize(new).map_err(|e| JsonError::New {
+        line: e.line(),
+        column: e.column(),
+    })?;
+    Ok(JsonDiff { old, new })
+}
+
+/// Parses `text` and pretty-prints it, `serde_json::Map` keeps keys sorted
+fn canonicalize(text: &str) -> Result<String, serde_json::Error> {
+    let value: serde_json::Value = serde_json::from_str(text)?;
+    let mut out = serde_json::to_string_pretty(&value)?;
+    out.push('\n');
+    Ok(out)
+}
+
+#[test]
+fn test_diff_json() {
+    let diff = diff_json(
+        r#"{"name": "x", "tags": ["a", "b"], "size": 1}"#,
+        "{\n  \"size\": 1,\n  \"tags\": [\"a\",\"b\"],\n  \"name\": \"x\"\n}",
+    )
+    .unwrap();
+    assert_eq!(diff.old, diff.new);
+    assert_eq!(diff.changeset().stats().insertions, 0);
+
+    let diff = diff_json(
+        r#"{"a": {"b": 1, "c": null}}"#,
+        r#"{"a": {"c": null, "b": 2}}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        diff.old,
+        "{\n  \"a\": {\n    \"b\": 1,\n    \"c\": null\n  }\n}\n"
+    );
+    assert_eq!(
+        diff.changeset().diff(),
+        vec![
+            crate::basic::DiffOp::Equal(&["{", "  \"a\": {"][..]),
+            crate::basic::DiffOp::Replace(&["    \"b\": 1,"][..], &["    \"b\": 2,"][..]),
+            crate::basic::DiffOp::Equal(&["    \"c\": null", "  }", "}"][..]),
+        ]
+    );
+}
+
+#[test]
+fn test_diff_json_invalid() {
+    assert_eq!(
+        diff_json("[1, 2", "[]"),
+        Err(JsonError::Old { line: 1, column: 5 })
+    );
+    assert_eq!(
+        diff_json("{}", "{\n  \"a\" 1}"),
+        Err(JsonError::New { line: 2, column: 7 })
+    );
+    assert_eq!(
+        JsonError::New { line: 2, column: 7 }.to_string(),
+        "invalid JSON in new document at 2:7"
+    );
+}
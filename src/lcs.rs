@@ -24,13 +24,23 @@ where
 {
     /// Creates new table for search common subsequences in x and y
     pub fn new(x: &'a [T], y: &'a [T]) -> Table<'a, T> {
+        Table::new_by(x, y, |a, b| a == b)
+    }
+}
+
+impl<'a, T> Table<'a, T> {
+    /// Creates new table for search common subsequences in x and y, elements are compared with `eq`
+    pub fn new_by<F>(x: &'a [T], y: &'a [T], eq: F) -> Table<'a, T>
+    where
+        F: Fn(&T, &T) -> bool,
+    {
         let x_len = x.len() + 1;
         let y_len = y.len() + 1;
         let mut table = vec![vec![0; y_len]; x_len];
 
         for i in 1..x_len {
             for j in 1..y_len {
-                table[i][j] = if x[i - 1] == y[j - 1] {
+                table[i][j] = if eq(&x[i - 1], &y[j - 1]) {
                     table[i - 1][j - 1] + 1
                 } else {
                     max(table[i][j - 1], table[i - 1][j])
@@ -41,14 +51,14 @@ where
         Table { x, y, table }
     }
 
-    fn seq_iter(&self) -> TableIter<T> {
+    fn seq_iter(&self) -> TableIter<'_, T> {
         TableIter {
             x: self.x.len(),
             y: self.y.len(),
             table: self,
         }
     }
-    fn get_match(&self, x: usize, y: usize, len: usize) -> Match<T> {
+    fn get_match(&self, x: usize, y: usize, len: usize) -> Match<'_, T> {
         Match {
             x,
             y,
@@ -58,7 +68,7 @@ where
     }
 
     /// Returns matches between X and Y
-    pub fn matches(&self) -> Vec<Match<T>> {
+    pub fn matches(&self) -> Vec<Match<'_, T>> {
         let mut matches: Vec<Match<T>> = Vec::new();
         for (x, y) in self.seq_iter() {
             if let Some(last) = matches.last_mut() {
@@ -76,12 +86,17 @@ where
     }
 
     /// Returns matches between X and Y with zero-len match at the end
-    pub fn matches_zero(&self) -> Vec<Match<T>> {
+    pub fn matches_zero(&self) -> Vec<Match<'_, T>> {
         let mut matches = self.matches();
         matches.push(self.get_match(self.x.len(), self.y.len(), 0));
         matches
     }
 
+    /// Length of longest sequence, without walking the table
+    pub fn longest_len(&self) -> usize {
+        self.table[self.x.len()][self.y.len()]
+    }
+
     /// Find longest sequence
     pub fn longest_seq(&self) -> Vec<&T> {
         self.matches();
@@ -91,7 +106,7 @@ where
     }
 }
 
-#[cfg(feature = "prettytable-rs")]
+#[cfg(feature = "table")]
 /// Prints pretty-table for LCS
 impl<'a, T> std::fmt::Display for Table<'a, T>
 where
@@ -165,6 +180,128 @@ impl<'a, T> Match<'a, T> {
     }
 }
 
+/// Above this many table cells [`lcs_indices`] switches to [`lcs_linear`] to save memory
+const LINEAR_THRESHOLD: usize = 1 << 24;
+
+/// Returns indices `(i, j)` of the longest common subsequence, `x[i] == y[j]`, in increasing order.
+/// This is the same subsequence [`basic::diff`](crate::basic::diff) is derived from: it comes
+/// from [`Table`] and from [`lcs_linear`] for large inputs.
+pub fn lcs_indices<T: PartialEq>(x: &[T], y: &[T]) -> Vec<(usize, usize)> {
+    lcs_indices_by(x, y, |a, b| a == b)
+}
+
+pub(crate) fn lcs_indices_by<T, F>(x: &[T], y: &[T], eq: F) -> Vec<(usize, usize)>
+where
+    F: Fn(&T, &T) -> bool,
+{
+    if x.len().saturating_mul(y.len()) > LINEAR_THRESHOLD {
+        return lcs_linear_by(x, y, eq);
+    }
+    let table = Table::new_by(x, y, eq);
+    let mut indices: Vec<_> = table.seq_iter().collect();
+    indices.reverse();
+    indices
+}
+
+/// Finds longest common subsequence with Hirschberg's algorithm: O(N*M) time, but only
+/// O(min(N, M)) memory instead of the full table. Returns indices `(x, y)` of matched elements in order
+pub fn lcs_linear<T: PartialEq>(x: &[T], y: &[T]) -> Vec<(usize, usize)> {
+    lcs_linear_by(x, y, |a, b| a == b)
+}
+
+/// Same as [`lcs_linear`], elements are compared with `eq`
+pub fn lcs_linear_by<T, F>(x: &[T], y: &[T], eq: F) -> Vec<(usize, usize)>
+where
+    F: Fn(&T, &T) -> bool,
+{
+    let mut out = Vec::new();
+    if y.len() > x.len() {
+        // Rows run along the shorter sequence
+        hirschberg(y, x, 0, 0, &|a, b| eq(b, a), &mut out);
+        out.iter_mut().for_each(|(i, j)| std::mem::swap(i, j));
+    } else {
+        hirschberg(x, y, 0, 0, &eq, &mut out);
+    }
+    out
+}
+
+fn hirschberg<T, F>(x: &[T], y: &[T], x0: usize, y0: usize, eq: &F, out: &mut Vec<(usize, usize)>)
+where
+    F: Fn(&T, &T) -> bool,
+{
+    let prefix = x.iter().zip(y).take_while(|(a, b)| eq(a, b)).count();
+    out.extend((0..prefix).map(|i| (x0 + i, y0 + i)));
+    let (x, y) = (&x[prefix..], &y[prefix..]);
+    let (x0, y0) = (x0 + prefix, y0 + prefix);
+    let suffix = x
+        .iter()
+        .rev()
+        .zip(y.iter().rev())
+        .take_while(|(a, b)| eq(a, b))
+        .count();
+    let (x, y) = (&x[..x.len() - suffix], &y[..y.len() - suffix]);
+
+    if x.len() == 1 {
+        if let Some(j) = y.iter().position(|b| eq(&x[0], b)) {
+            out.push((x0, y0 + j));
+        }
+    } else if !x.is_empty() && !y.is_empty() {
+        let mid = x.len() / 2;
+        let forward = lcs_row(mid, y.len(), |i, j| eq(&x[i], &y[j]));
+        let backward = lcs_row(x.len() - mid, y.len(), |i, j| {
+            eq(&x[x.len() - 1 - i], &y[y.len() - 1 - j])
+        });
+        // forward[j] is LCS of x[..mid] and y[..j], backward[k] of x[mid..] and y[y.len() - k..]
+        let split = (0..=y.len())
+            .rev()
+            .max_by_key(|&j| forward[j] + backward[y.len() - j])
+            .unwrap_or(0);
+        hirschberg(&x[..mid], &y[..split], x0, y0, eq, out);
+        hirschberg(&x[mid..], &y[split..], x0 + mid, y0 + split, eq, out);
+    }
+
+    let (x_end, y_end) = (x0 + x.len(), y0 + y.len());
+    out.extend((0..suffix).map(|i| (x_end + i, y_end + i)));
+}
+
+/// Length of the longest common subsequence without reconstructing it: a single row of the
+/// table is kept, O(N*M) time and O(min(N, M)) memory
+pub fn lcs_len<T: PartialEq>(x: &[T], y: &[T]) -> usize {
+    let prefix = x.iter().zip(y).take_while(|(a, b)| a == b).count();
+    let (x, y) = (&x[prefix..], &y[prefix..]);
+    let suffix = x
+        .iter()
+        .rev()
+        .zip(y.iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (x, y) = (&x[..x.len() - suffix], &y[..y.len() - suffix]);
+    let (x, y) = if y.len() > x.len() { (y, x) } else { (x, y) };
+    prefix + suffix + lcs_row(x.len(), y.len(), |i, j| x[i] == y[j])[y.len()]
+}
+
+/// Last row of LCS table for sequences of length `x_len` and `y_len`
+fn lcs_row<F>(x_len: usize, y_len: usize, eq: F) -> Vec<usize>
+where
+    F: Fn(usize, usize) -> bool,
+{
+    let mut row = vec![0; y_len + 1];
+    for i in 0..x_len {
+        let mut diagonal = 0;
+        for j in 1..=y_len {
+            let above = row[j];
+            row[j] = if eq(i, j - 1) {
+                diagonal + 1
+            } else {
+                max(row[j - 1], above)
+            };
+            diagonal = above;
+        }
+    }
+    row
+}
+
+#[cfg(feature = "table")]
 #[test]
 fn test_table() {
     let x = vec!["A", "G", "C", "A", "T"];
@@ -225,3 +362,52 @@ fn test_table_match() {
         assert_eq!(matches, match_exp);
     }
 }
+
+#[test]
+fn test_lcs_linear() {
+    let x = vec!["A", "G", "C", "A", "T"];
+    let y = vec!["G", "A", "C"];
+    let pairs = lcs_linear(&x, &y);
+    assert_eq!(pairs.len(), 2);
+    assert!(pairs.iter().all(|&(i, j)| x[i] == y[j]));
+
+    // Compare with the full table on pseudo-random inputs
+    let mut seed = 42u32;
+    let mut rand = |n: u32| {
+        seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+        (seed >> 16) % n
+    };
+    for _ in 0..200 {
+        let x: Vec<_> = (0..rand(30)).map(|_| rand(4)).collect();
+        let y: Vec<_> = (0..rand(30)).map(|_| rand(4)).collect();
+        let pairs = lcs_linear(&x, &y);
+        assert_eq!(pairs.len(), Table::new(&x, &y).longest_len());
+        assert!(pairs.iter().all(|&(i, j)| x[i] == y[j]));
+        assert!(pairs.windows(2).all(|w| w[0].0 < w[1].0 && w[0].1 < w[1].1));
+    }
+}
+
+#[test]
+fn test_lcs_indices() {
+    let x = ["a", "b", "c", "d"];
+    let y = ["b", "x", "d"];
+    assert_eq!(lcs_indices(&x, &y), vec![(1, 0), (3, 2)]);
+    assert_eq!(lcs_indices::<u8>(&[], &[1]), vec![]);
+}
+
+#[test]
+fn test_lcs_len() {
+    assert_eq!(lcs_len(&["A", "G", "C", "A", "T"], &["G", "A", "C"]), 2);
+    assert_eq!(lcs_len::<u8>(&[], &[1]), 0);
+    let mut seed = 7u32;
+    let mut rand = |n: u32| {
+        seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+        (seed >> 16) % n
+    };
+    for _ in 0..200 {
+        let x: Vec<_> = (0..rand(30)).map(|_| rand(4)).collect();
+        let y: Vec<_> = (0..rand(30)).map(|_| rand(4)).collect();
+        assert_eq!(lcs_len(&x, &y), Table::new(&x, &y).longest_seq().len());
+        assert_eq!(lcs_len(&x, &y), lcs_indices(&x, &y).len());
+    }
+}
@@ -1,19 +1,24 @@
 macro_rules! cfg_prettytable {( $($item:item)* ) => (
     $(
-        #[cfg(feature = "prettytable-rs")]
+        #[cfg(feature = "table")]
         $item
     )*
 )}
 
-#[cfg(feature = "prettytable-rs")]
-#[macro_use]
+#[cfg(feature = "table")]
 extern crate prettytable;
 
 pub mod basic;
 cfg_prettytable! {
     pub mod format_table;
 }
+#[cfg(feature = "fs")]
+pub mod fs;
+#[cfg(feature = "json")]
+pub mod json;
 pub mod lcs;
+#[cfg(feature = "macros")]
+pub mod macros;
 pub mod text;
 
 pub use crate::basic::diff_slice;
@@ -0,0 +1,77 @@
+//! [`assert_diff_eq!`](crate::assert_diff_eq), enabled by the `macros` feature
+
+use crate::text::{diff_lines, ColorMode};
+
+/// Asserts that two strings are equal like [`assert_eq!`], on failure the panic message shows
+/// a line diff of both. The diff is colored if stdout is a terminal and `NO_COLOR` isn't set,
+/// otherwise changed lines are marked with `-` and `+`.
+///
+/// ```
+/// use prettydiff::assert_diff_eq;
+///
+/// assert_diff_eq!("a\nb\n", String::from("a\nb\n"));
+/// assert_diff_eq!("a", "a", "values for {} differ", "a");
+/// ```
+#[macro_export]
+macro_rules! assert_diff_eq {
+    ($left:expr, $right:expr $(,)?) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                let (left, right): (&str, &str) = (left.as_ref(), right.as_ref());
+                if left != right {
+                    panic!("{}", $crate::macros::failure_message(left, right, None));
+                }
+            }
+        }
+    };
+    ($left:expr, $right:expr, $($arg:tt)+) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                let (left, right): (&str, &str) = (left.as_ref(), right.as_ref());
+                if left != right {
+                    panic!(
+                        "{}",
+                        $crate::macros::failure_message(left, right, Some(format_args!($($arg)+)))
+                    );
+                }
+            }
+        }
+    };
+}
+
+#[doc(hidden)]
+pub fn failure_message(left: &str, right: &str, message: Option<std::fmt::Arguments>) -> String {
+    render(left, right, message, ColorMode::Auto)
+}
+
+fn render(
+    left: &str,
+    right: &str,
+    message: Option<std::fmt::Arguments>,
+    color_mode: ColorMode,
+) -> String {
+    let diff = diff_lines(left, right).set_color_mode(color_mode).format();
+    match message {
+        Some(message) => format!("assertion `left == right` failed: {}\n{}", message, diff),
+        None => format!("assertion `left == right` failed\n{}", diff),
+    }
+}
+
+#[test]
+fn test_failure_message() {
+    assert_eq!(
+        render("a\nb\n", "a\nc\n", None, ColorMode::Never),
+        "assertion `left == right` failed\na\n-b\n+c"
+    );
+    assert_eq!(
+        render("x", "y", Some(format_args!("case {}", 1)), ColorMode::Never),
+        "assertion `left == right` failed: case 1\n-x\n+y"
+    );
+}
+
+#[test]
+#[should_panic(expected = "assertion `left == right` failed: lines differ")]
+fn test_assert_diff_eq() {
+    assert_diff_eq!("same", String::from("same"));
+    assert_diff_eq!("a\nb", "a\nc", "lines differ");
+}
@@ -0,0 +1,132 @@
+//! Side-by-side diff for two files
+use prettydiff::text::{ColorMode, LineChangeset};
+use prettydiff::{basic::DiffOp, diff_lines};
+use std::io::Read;
+use std::process::exit;
+
+const USAGE: &str = "\
+Usage: prettydiff [OPTIONS] <OLD> <NEW>
+
+Compares two files line by line, `-` reads one of them from stdin.
+Exits with 0 if inputs are the same, 1 if they differ and 2 on errors.
+
+Options:
+    --diff-only                            Show only changed lines in side-by-side diff
+    --no-line-numbers                      Don't show line numbers
+    --format <side-by-side|inline|unified> Output format [default: side-by-side]
+    --color <always|never|auto>            When to use colors [default: auto]
+    -h, --help                             Print help";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    SideBySide,
+    Inline,
+    Unified,
+}
+
+struct Options {
+    diff_only: bool,
+    line_numbers: bool,
+    format: Format,
+    color: ColorMode,
+    old: String,
+    new: String,
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("prettydiff: {}", message);
+    exit(2)
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Options {
+    let mut options = Options {
+        diff_only: false,
+        line_numbers: true,
+        format: Format::SideBySide,
+        color: ColorMode::Auto,
+        old: String::new(),
+        new: String::new(),
+    };
+    let mut files = Vec::new();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .unwrap_or_else(|| fail(&format!("{} requires a value", name)))
+        };
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                exit(0)
+            }
+            "--diff-only" => options.diff_only = true,
+            "--no-line-numbers" => options.line_numbers = false,
+            "--format" => {
+                options.format = match value("--format").as_str() {
+                    "side-by-side" => Format::SideBySide,
+                    "inline" => Format::Inline,
+                    "unified" => Format::Unified,
+                    other => fail(&format!("unknown format `{}`", other)),
+                }
+            }
+            "--color" => {
+                options.color = match value("--color").as_str() {
+                    "always" => ColorMode::Always,
+                    "never" => ColorMode::Never,
+                    "auto" => ColorMode::Auto,
+                    other => fail(&format!("unknown color mode `{}`", other)),
+                }
+            }
+            _ if arg.starts_with("--") => fail(&format!("unknown option `{}`\n\n{}", arg, USAGE)),
+            _ => files.push(arg),
+        }
+    }
+    match &mut files[..] {
+        [old, new] => {
+            options.old = std::mem::take(old);
+            options.new = std::mem::take(new);
+        }
+        _ => fail(&format!("expected two files\n\n{}", USAGE)),
+    }
+    if options.old == "-" && options.new == "-" {
+        fail("only one input can be read from stdin");
+    }
+    options
+}
+
+fn read(path: &str) -> String {
+    let result = if path == "-" {
+        let mut text = String::new();
+        std::io::stdin().read_to_string(&mut text).map(|_| text)
+    } else {
+        std::fs::read_to_string(path)
+    };
+    result.unwrap_or_else(|e| fail(&format!("{}: {}", path, e)))
+}
+
+fn print(changeset: &LineChangeset, options: &Options) {
+    match options.format {
+        Format::SideBySide => changeset.prettytable(),
+        Format::Inline => println!(
+            "{}",
+            changeset.format_with_context(None, options.line_numbers)
+        ),
+        Format::Unified => print!("{}", changeset.format_unified(3)),
+    }
+}
+
+fn main() {
+    let options = parse_args(std::env::args().skip(1));
+    let (old, new) = (read(&options.old), read(&options.new));
+    let changeset = diff_lines(&old, &new)
+        .names(&options.old, &options.new)
+        .set_diff_only(options.diff_only)
+        .set_show_lines(options.line_numbers)
+        .set_color_mode(options.color);
+    let changed = changeset
+        .diff()
+        .iter()
+        .any(|op| !matches!(op, DiffOp::Equal(_)))
+        || old.ends_with('\n') != new.ends_with('\n');
+    print(&changeset, &options);
+    exit(i32::from(changed))
+}
@@ -1,6 +1,8 @@
 //! Utils for diff text
+#[cfg(feature = "ansi")]
 use owo_colors::AnsiColors::{Green, Red};
-use owo_colors::{AnsiColors, OwoColorize, Style};
+#[cfg(feature = "ansi")]
+use owo_colors::{AnsiColors, Effect, OwoColorize, Style};
 
 use crate::basic;
 cfg_prettytable! {
@@ -8,11 +10,16 @@ cfg_prettytable! {
     use prettytable::{Cell, Row};
 }
 use std::{
+    borrow::Cow,
     cmp::{max, min},
     fmt,
+    io::{self, BufRead, IsTerminal},
+    ops::Range,
+    sync::Arc,
 };
 
 use pad::{Alignment, PadStr};
+use unicode_width::UnicodeWidthChar;
 
 pub struct StringSplitIter<'a, F>
 where
@@ -75,75 +82,414 @@ pub fn split_words(text: &str) -> impl Iterator<Item = &str> {
 }
 
 /// Container for inline text diff result. Can be pretty-printed by Display trait.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct InlineChangeset<'a> {
     old: Vec<&'a str>,
     new: Vec<&'a str>,
     separator: &'a str,
+    #[cfg(feature = "ansi")]
     highlight_whitespace: bool,
+    #[cfg(feature = "ansi")]
     insert_style: Style,
+    #[cfg(feature = "ansi")]
     insert_whitespace_style: Style,
+    #[cfg(feature = "ansi")]
     remove_style: Style,
+    #[cfg(feature = "ansi")]
     remove_whitespace_style: Style,
+    #[cfg(feature = "ansi")]
+    strikethrough: bool,
+    annotate_whitespace: bool,
+    chunk_size: Option<usize>,
+    word_eq: Option<Comparator>,
+    color: ColorMode,
+    whitespace_markers: bool,
+    ignore_case: bool,
+    nested_char_highlight: bool,
+}
+
+type EqFn = dyn Fn(&str, &str) -> bool + Send + Sync;
+
+/// Custom equality of tokens, two comparators are equal only if they are the same closure
+#[derive(Clone)]
+struct Comparator(Arc<EqFn>);
+
+impl Comparator {
+    fn new(eq: impl Fn(&str, &str) -> bool + Send + Sync + 'static) -> Self {
+        Comparator(Arc::new(eq))
+    }
+
+    fn eq(&self, a: &str, b: &str) -> bool {
+        (self.0)(a, b)
+    }
+}
+
+impl fmt::Debug for Comparator {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "Comparator")
+    }
+}
+
+impl PartialEq for Comparator {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Comparator {}
+
+/// Recognizes section header lines for [`LineChangeset::set_hunk_header_hint`], compared like
+/// [`Comparator`]
+#[derive(Clone)]
+struct HeaderHint(Arc<dyn Fn(&str) -> bool + Send + Sync>);
+
+impl fmt::Debug for HeaderHint {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "HeaderHint")
+    }
+}
+
+impl PartialEq for HeaderHint {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for HeaderHint {}
+
+/// Builds the marker of collapsed unchanged lines from their count for
+/// [`LineChangeset::set_fold_marker_with`], compared like [`Comparator`]
+#[derive(Clone)]
+struct FoldMarker(Arc<dyn Fn(usize) -> String + Send + Sync>);
+
+impl fmt::Debug for FoldMarker {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "FoldMarker")
+    }
+}
+
+impl PartialEq for FoldMarker {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for FoldMarker {}
+
+/// Kind of whitespace-only change, see [`InlineChangeset::whitespace_changes`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WhitespaceChange {
+    /// Spaces were replaced by tabs
+    SpacesToTabs,
+    /// Tabs were replaced by spaces
+    TabsToSpaces,
+    /// Amount of whitespace between words changed
+    Count,
+    /// Whitespace added or removed at the beginning
+    Leading,
+    /// Whitespace added or removed at the end
+    Trailing,
+    /// Any other whitespace change, e.g. line breaks
+    Other,
+}
+
+impl fmt::Display for WhitespaceChange {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let text = match self {
+            WhitespaceChange::SpacesToTabs => "spaces to tabs",
+            WhitespaceChange::TabsToSpaces => "tabs to spaces",
+            WhitespaceChange::Count => "whitespace count",
+            WhitespaceChange::Leading => "leading whitespace",
+            WhitespaceChange::Trailing => "trailing whitespace",
+            WhitespaceChange::Other => "whitespace",
+        };
+        write!(formatter, "{}", text)
+    }
+}
+
+/// Compares lowercase forms of `a` and `b`
+fn eq_ignore_case(a: &str, b: &str) -> bool {
+    a.chars()
+        .flat_map(char::to_lowercase)
+        .eq(b.chars().flat_map(char::to_lowercase))
+}
+
+fn is_whitespace(s: &str) -> bool {
+    s.chars().all(char::is_whitespace)
 }
 
 impl<'a> InlineChangeset<'a> {
+    /// Diff already split tokens, which are output as they are without a separator.
+    /// Use [`from_tokens`](Self::from_tokens) if the tokens don't contain their separators
     pub fn new(old: Vec<&'a str>, new: Vec<&'a str>) -> InlineChangeset<'a> {
         InlineChangeset {
             old,
             new,
             separator: "",
+            #[cfg(feature = "ansi")]
             highlight_whitespace: true,
+            #[cfg(feature = "ansi")]
             insert_style: Style::new().green(),
+            #[cfg(feature = "ansi")]
             insert_whitespace_style: Style::new().white().on_green(),
+            #[cfg(feature = "ansi")]
             remove_style: Style::new().red().strikethrough(),
+            #[cfg(feature = "ansi")]
             remove_whitespace_style: Style::new().white().on_red(),
+            #[cfg(feature = "ansi")]
+            strikethrough: true,
+            annotate_whitespace: false,
+            chunk_size: None,
+            word_eq: None,
+            color: ColorMode::default(),
+            whitespace_markers: false,
+            ignore_case: false,
+            nested_char_highlight: false,
         }
     }
-    /// Highlight whitespaces in case of insert/remove?
+    /// Diff already split tokens, `separator` is put between tokens in the output like with
+    /// [`set_separator`](Self::set_separator)
+    ///
+    /// ```
+    /// use prettydiff::text::InlineChangeset;
+    ///
+    /// let changeset = InlineChangeset::from_tokens(vec!["a", "b", "c"], vec!["a", "x", "c"], " ");
+    /// assert_eq!(changeset.set_color(false).format(), "a [-b-]{+x+} c");
+    /// ```
+    pub fn from_tokens(
+        old: Vec<&'a str>,
+        new: Vec<&'a str>,
+        separator: &'a str,
+    ) -> InlineChangeset<'a> {
+        InlineChangeset::new(old, new).set_separator(separator)
+    }
+
+    /// Highlight whitespaces in case of insert/remove? Insert and remove styles with a background
+    /// color (like `Style::new().black().on_green()`) are kept for whitespace too
+    #[cfg(feature = "ansi")]
     pub fn set_highlight_whitespace(mut self, val: bool) -> Self {
         self.highlight_whitespace = val;
         self
     }
 
     /// Style of inserted text
+    #[cfg(feature = "ansi")]
     pub fn set_insert_style(mut self, val: Style) -> Self {
         self.insert_style = val;
         self
     }
 
     /// Style of inserted whitespace
+    #[cfg(feature = "ansi")]
     pub fn set_insert_whitespace_style(mut self, val: Style) -> Self {
         self.insert_whitespace_style = val;
         self
     }
 
     /// Style of removed text
+    #[cfg(feature = "ansi")]
     pub fn set_remove_style(mut self, val: Style) -> Self {
         self.remove_style = val;
         self
     }
 
     /// Style of removed whitespace
+    #[cfg(feature = "ansi")]
     pub fn set_remove_whitespace_style(mut self, val: Style) -> Self {
         self.remove_whitespace_style = val;
         self
     }
 
-    /// Set output separator
+    /// Strike through removed text (default), disable for terminals without strikethrough support
+    #[cfg(feature = "ansi")]
+    pub fn set_strikethrough(mut self, val: bool) -> Self {
+        self.strikethrough = val;
+        self
+    }
+
+    /// Show spaces as `·` and tabs as `→` in inserted and removed text
+    pub fn set_whitespace_markers(mut self, val: bool) -> Self {
+        self.whitespace_markers = val;
+        self
+    }
+
+    /// Set output separator, put between tokens but not between the old and new tokens
+    /// of a replace
     pub fn set_separator(mut self, val: &'a str) -> Self {
         self.separator = val;
         self
     }
 
+    /// Add a note like `[tabs to spaces]` after whitespace-only changes
+    pub fn set_annotate_whitespace(mut self, val: bool) -> Self {
+        self.annotate_whitespace = val;
+        self
+    }
+
+    /// Diff inputs longer than `val` tokens in windows of `val` tokens to bound time and memory
+    /// usage, see [`basic::diff_chunked`]. The result may be larger than the minimal diff, so
+    /// inputs are diffed as a whole unless this is set
+    pub fn set_chunk_size(mut self, val: usize) -> Self {
+        self.chunk_size = Some(val);
+        self
+    }
+
+    /// Use colors in output (default unless `NO_COLOR` is set), without colors changes are
+    /// marked like `[-removed-]{+inserted+}`
+    pub fn set_color(mut self, val: bool) -> Self {
+        self.color = if val {
+            ColorMode::Always
+        } else {
+            ColorMode::Never
+        };
+        self
+    }
+    /// When to use colors, see [`ColorMode`]
+    pub fn set_color_mode(mut self, val: ColorMode) -> Self {
+        self.color = val;
+        self
+    }
+
+    /// Compare tokens with `eq` instead of `==`, output still shows the original tokens
+    pub fn set_word_eq(mut self, eq: impl Fn(&str, &str) -> bool + Send + Sync + 'static) -> Self {
+        self.word_eq = Some(Comparator::new(eq));
+        self
+    }
+
+    /// Diff replaced tokens again by characters in [`format`](Self::format), so only the changed
+    /// characters are highlighted (`colo{+u+}r` instead of `[-color-]{+colour+}`). Replaces
+    /// sharing less than half of their characters stay highlighted as a whole
+    pub fn set_nested_char_highlight(mut self, val: bool) -> Self {
+        self.nested_char_highlight = val;
+        self
+    }
+
+    /// Compare tokens ignoring case, output still shows the original casing.
+    /// Has no effect with [`set_word_eq`](Self::set_word_eq)
+    pub fn set_ignore_case(mut self, val: bool) -> Self {
+        self.ignore_case = val;
+        self
+    }
+
     /// Returns Vec of changes
     pub fn diff(&self) -> Vec<basic::DiffOp<'a, &str>> {
-        basic::diff(&self.old, &self.new)
+        let eq = |a: &&str, b: &&str| match &self.word_eq {
+            Some(word_eq) => word_eq.eq(a, b),
+            None if self.ignore_case => eq_ignore_case(a, b),
+            None => a == b,
+        };
+        match self.chunk_size {
+            Some(size) if max(self.old.len(), self.new.len()) > size => {
+                basic::diff_chunked_by(&self.old, &self.new, size, eq)
+            }
+            _ => basic::diff_by(&self.old, &self.new, eq),
+        }
+    }
+
+    /// Similarity of both texts from 0.0 to 1.0, see [`basic::ratio`]
+    pub fn ratio(&self) -> f64 {
+        basic::ops_ratio(&self.diff())
+    }
+
+    /// Numbers of inserted and removed characters (Unicode scalar values), changed tokens of
+    /// one op count with the separators between them
+    pub fn char_stats(&self) -> (usize, usize) {
+        let chars = |tokens: &[&str]| tokens.join(self.separator).chars().count();
+        self.diff()
+            .iter()
+            .filter(|op| op.is_change())
+            .fold((0, 0), |(inserted, removed), op| {
+                (
+                    inserted + chars(op.new_slice()),
+                    removed + chars(op.old_slice()),
+                )
+            })
+    }
+
+    /// Whether the texts differ. Identical texts aren't diffed
+    pub fn has_changes(&self) -> bool {
+        self.old != self.new && self.diff().iter().any(basic::DiffOp::is_change)
+    }
+
+    /// Returns kinds of all whitespace-only changes, in order
+    pub fn whitespace_changes(&self) -> Vec<WhitespaceChange> {
+        let diff = self.diff();
+        (0..diff.len())
+            .filter_map(|index| self.classify_whitespace(&diff, index))
+            .collect()
+    }
+
+    /// Classifies change at `index`, returns None for equal or non-whitespace changes
+    fn classify_whitespace(
+        &self,
+        diff: &[basic::DiffOp<&str>],
+        index: usize,
+    ) -> Option<WhitespaceChange> {
+        let texts = |op: &basic::DiffOp<&str>| match op {
+            basic::DiffOp::Equal(a) => (a.join(self.separator), a.join(self.separator)),
+            basic::DiffOp::Insert(b) => (String::new(), b.join(self.separator)),
+            basic::DiffOp::Remove(a) => (a.join(self.separator), String::new()),
+            basic::DiffOp::Replace(a, b) => (a.join(self.separator), b.join(self.separator)),
+        };
+        let only_whitespace = |ops: &[basic::DiffOp<&str>]| {
+            ops.iter().all(|op| {
+                let (old, new) = texts(op);
+                is_whitespace(&old) && is_whitespace(&new)
+            })
+        };
+        if let basic::DiffOp::Equal(_) = diff[index] {
+            return None;
+        }
+        let (old, new) = texts(&diff[index]);
+        if !is_whitespace(&old) || !is_whitespace(&new) {
+            return None;
+        }
+        let only = |s: &str, c: char| s.chars().all(|i| i == c);
+        let kind = if !old.is_empty() && !new.is_empty() {
+            if only(&old, ' ') && only(&new, '\t') {
+                WhitespaceChange::SpacesToTabs
+            } else if only(&old, '\t') && only(&new, ' ') {
+                WhitespaceChange::TabsToSpaces
+            } else if old.chars().all(|c| new.contains(c)) && new.chars().all(|c| old.contains(c)) {
+                WhitespaceChange::Count
+            } else {
+                WhitespaceChange::Other
+            }
+        } else if only_whitespace(&diff[..index]) {
+            WhitespaceChange::Leading
+        } else if only_whitespace(&diff[index + 1..]) {
+            WhitespaceChange::Trailing
+        } else {
+            let (before, _) = texts(&diff[index - 1]);
+            let after = diff.get(index + 1).map(texts).unwrap_or_default().0;
+            let next_to_whitespace =
+                before.ends_with(char::is_whitespace) || after.starts_with(char::is_whitespace);
+            if next_to_whitespace && !old.contains('\n') && !new.contains('\n') {
+                WhitespaceChange::Count
+            } else {
+                WhitespaceChange::Other
+            }
+        };
+        Some(kind)
+    }
+
+    /// `s` with whitespace markers if they are enabled
+    fn visible(&self, s: &str) -> String {
+        if self.whitespace_markers {
+            s.replace(' ', "·").replace('\t', "→")
+        } else {
+            s.to_string()
+        }
     }
 
+    /// Styles `a` with `style`, whitespace with `whitespace_style` if `highlight_whitespace` is set.
+    /// A `style` with a background already highlights whitespace and is used for all of `a`.
+    #[cfg(feature = "ansi")]
     fn apply_style(&self, style: Style, whitespace_style: Style, a: &[&str]) -> String {
         let s = a.join(self.separator);
-        if self.highlight_whitespace {
+        let has_background = style.remove_bg() != style;
+        let visible = |s: &str| self.visible(s);
+        if self.highlight_whitespace && !has_background {
             collect_strings(split_by_char_fn(&s, |c| c.is_whitespace()).map(|s| {
                 let style = if s
                     .chars()
@@ -154,38 +500,163 @@ impl<'a> InlineChangeset<'a> {
                 } else {
                     style
                 };
-                s.style(style).to_string()
+                visible(s).style(style).to_string()
             }))
             .join("")
         } else {
-            s.style(style).to_string()
+            visible(&s).style(style).to_string()
         }
     }
 
     fn remove_color(&self, a: &[&str]) -> String {
-        self.apply_style(self.remove_style, self.remove_whitespace_style, a)
+        #[cfg(feature = "ansi")]
+        if self.color.enabled() {
+            let style = if self.strikethrough {
+                self.remove_style
+            } else {
+                self.remove_style.remove_effect(Effect::Strikethrough)
+            };
+            return self.apply_style(style, self.remove_whitespace_style, a);
+        }
+        format!("[-{}-]", self.visible(&a.join(self.separator)))
     }
 
     fn insert_color(&self, a: &[&str]) -> String {
-        self.apply_style(self.insert_style, self.insert_whitespace_style, a)
+        #[cfg(feature = "ansi")]
+        if self.color.enabled() {
+            return self.apply_style(self.insert_style, self.insert_whitespace_style, a);
+        }
+        format!("{{+{}+}}", self.visible(&a.join(self.separator)))
+    }
+    /// Returns the tokens and separators of the diff tagged by change, for renderers with their
+    /// own styling. Like [`format`](Self::format), a replace has no separator between its
+    /// removed and inserted tokens.
+    pub fn spans(&self) -> Vec<(ChangeTag, &str)> {
+        tagged_spans(&self.diff(), self.separator)
     }
+
     /// Returns formatted string with colors
     pub fn format(&self) -> String {
         let diff = self.diff();
         let mut out: Vec<String> = Vec::with_capacity(diff.len());
-        for op in diff {
+        for (index, op) in diff.iter().enumerate() {
             match op {
                 basic::DiffOp::Equal(a) => out.push(a.join(self.separator)),
                 basic::DiffOp::Insert(a) => out.push(self.insert_color(a)),
                 basic::DiffOp::Remove(a) => out.push(self.remove_color(a)),
+                // Old and new tokens take the same place, no separator between them
                 basic::DiffOp::Replace(a, b) => {
-                    out.push(self.remove_color(a));
-                    out.push(self.insert_color(b));
+                    out.push(
+                        self.format_nested_chars(a, b)
+                            .unwrap_or_else(|| self.remove_color(a) + &self.insert_color(b)),
+                    );
+                }
+            }
+            if self.annotate_whitespace {
+                if let Some(kind) = self.classify_whitespace(&diff, index) {
+                    if let Some(last) = out.last_mut() {
+                        let note = format!("[{}]", kind);
+                        #[cfg(feature = "ansi")]
+                        let note = if self.color.enabled() {
+                            note.dimmed().to_string()
+                        } else {
+                            note
+                        };
+                        last.push_str(&note);
+                    }
                 }
             }
         }
         out.join(self.separator)
     }
+
+    /// Rebuilds the old text from [`diff`](Self::diff), tokens joined by the separator
+    pub fn reconstruct_old(&self) -> String {
+        collect_strings(self.diff().iter().flat_map(|op| op.old_slice())).join(self.separator)
+    }
+
+    /// Rebuilds the new text from [`diff`](Self::diff), tokens joined by the separator
+    pub fn reconstruct_new(&self) -> String {
+        collect_strings(self.diff().iter().flat_map(|op| op.new_slice())).join(self.separator)
+    }
+
+    /// Formats a replace diffed again by characters with `nested_char_highlight`, if at least
+    /// half of the characters are the same
+    fn format_nested_chars(&self, a: &[&str], b: &[&str]) -> Option<String> {
+        if !self.nested_char_highlight {
+            return None;
+        }
+        fn split(s: &str) -> Vec<&str> {
+            s.split("").filter(|i| !i.is_empty()).collect()
+        }
+        let (old, new) = (a.join(self.separator), b.join(self.separator));
+        let mut chars = self.with_tokens(split(&old), split(&new), "");
+        chars.word_eq = None;
+        chars.annotate_whitespace = false;
+        chars.nested_char_highlight = false;
+        if chars.ratio() < 0.5 {
+            return None;
+        }
+        Some(chars.format())
+    }
+
+    /// Returns [`format`](Self::format) on a single line, with `\\`, `\n` and `\r` escaped like in
+    /// Rust strings, for log output
+    pub fn format_compact(&self) -> String {
+        let mut out = String::new();
+        for c in self.format().chars() {
+            match c {
+                '\\' => out.push_str("\\\\"),
+                '\n' => out.push_str("\\n"),
+                '\r' => out.push_str("\\r"),
+                c => out.push(c),
+            }
+        }
+        out
+    }
+
+    /// Copies the settings into a changeset of other tokens
+    fn with_tokens<'b>(
+        &self,
+        old: Vec<&'b str>,
+        new: Vec<&'b str>,
+        separator: &'b str,
+    ) -> InlineChangeset<'b> {
+        InlineChangeset {
+            old,
+            new,
+            separator,
+            #[cfg(feature = "ansi")]
+            highlight_whitespace: self.highlight_whitespace,
+            #[cfg(feature = "ansi")]
+            insert_style: self.insert_style,
+            #[cfg(feature = "ansi")]
+            insert_whitespace_style: self.insert_whitespace_style,
+            #[cfg(feature = "ansi")]
+            remove_style: self.remove_style,
+            #[cfg(feature = "ansi")]
+            remove_whitespace_style: self.remove_whitespace_style,
+            #[cfg(feature = "ansi")]
+            strikethrough: self.strikethrough,
+            annotate_whitespace: self.annotate_whitespace,
+            chunk_size: self.chunk_size,
+            word_eq: self.word_eq.clone(),
+            color: self.color,
+            whitespace_markers: self.whitespace_markers,
+            ignore_case: self.ignore_case,
+            nested_char_highlight: self.nested_char_highlight,
+        }
+    }
+
+    /// Copies the tokens so the changeset no longer borrows its inputs
+    pub fn into_owned(self) -> InlineChangesetOwned {
+        InlineChangesetOwned {
+            old: collect_strings(self.old.iter()),
+            new: collect_strings(self.new.iter()),
+            separator: self.separator.to_string(),
+            settings: self.with_tokens(Vec::new(), Vec::new(), ""),
+        }
+    }
 }
 
 impl<'a> fmt::Display for InlineChangeset<'a> {
@@ -194,6 +665,36 @@ impl<'a> fmt::Display for InlineChangeset<'a> {
     }
 }
 
+/// [`InlineChangeset`] owning its tokens, see [`InlineChangeset::into_owned`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct InlineChangesetOwned {
+    old: Vec<String>,
+    new: Vec<String>,
+    separator: String,
+    settings: InlineChangeset<'static>,
+}
+
+impl InlineChangesetOwned {
+    /// Borrowing changeset with the same settings, for all other methods
+    pub fn changeset(&self) -> InlineChangeset<'_> {
+        self.settings.with_tokens(
+            self.old.iter().map(String::as_str).collect(),
+            self.new.iter().map(String::as_str).collect(),
+            &self.separator,
+        )
+    }
+
+    pub fn format(&self) -> String {
+        self.changeset().format()
+    }
+}
+
+impl fmt::Display for InlineChangesetOwned {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{}", self.format())
+    }
+}
+
 pub fn diff_chars<'a>(old: &'a str, new: &'a str) -> InlineChangeset<'a> {
     let old: Vec<&str> = old.split("").filter(|&i| !i.is_empty()).collect();
     let new: Vec<&str> = new.split("").filter(|&i| !i.is_empty()).collect();
@@ -201,706 +702,4319 @@ pub fn diff_chars<'a>(old: &'a str, new: &'a str) -> InlineChangeset<'a> {
     InlineChangeset::new(old, new)
 }
 
+/// Returns up to `n` of `possibilities` with a [`ratio`](InlineChangeset::ratio) of
+/// [`diff_chars`] to `word` of at least `cutoff`, best first, like Python's
+/// `difflib.get_close_matches` (which uses `n = 3` and `cutoff = 0.6` by default)
+pub fn get_close_matches<'b>(
+    word: &str,
+    possibilities: &[&'b str],
+    n: usize,
+    cutoff: f64,
+) -> Vec<&'b str> {
+    let mut scored: Vec<(f64, &str)> = possibilities
+        .iter()
+        .map(|&possibility| (diff_chars(word, possibility).ratio(), possibility))
+        .filter(|&(ratio, _)| ratio >= cutoff)
+        .collect();
+    // Ties go to the greater string, as in Python
+    scored.sort_by(|a, b| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+    scored.into_iter().take(n).map(|(_, s)| s).collect()
+}
+
+/// Diff two strings by extended grapheme clusters (user-perceived characters), so emoji
+/// sequences and combining marks are never split
+#[cfg(feature = "unicode")]
+pub fn diff_graphemes<'a>(old: &'a str, new: &'a str) -> InlineChangeset<'a> {
+    use unicode_segmentation::UnicodeSegmentation;
+
+    InlineChangeset::new(old.graphemes(true).collect(), new.graphemes(true).collect())
+}
+
+/// Like [`split_words`], but punctuation following a word stays part of it, so `"foo, bar"`
+/// is split into `["foo,", " ", "bar"]`
+pub fn split_words_punctuated(text: &str) -> impl Iterator<Item = &str> {
+    let mut rest = text;
+    std::iter::from_fn(move || {
+        let mut chars = rest.char_indices();
+        let (_, first) = chars.next()?;
+        let mut end = first.len_utf8();
+        if first.is_alphanumeric() {
+            let mut punctuation = false;
+            for (index, c) in chars {
+                if c.is_whitespace() || (c.is_alphanumeric() && punctuation) {
+                    break;
+                }
+                punctuation |= !c.is_alphanumeric();
+                end = index + c.len_utf8();
+            }
+        }
+        let (token, tail) = rest.split_at(end);
+        rest = tail;
+        Some(token)
+    })
+}
+
 /// Diff two strings by words (contiguous)
 pub fn diff_words<'a>(old: &'a str, new: &'a str) -> InlineChangeset<'a> {
-    InlineChangeset::new(split_words(old).collect(), split_words(new).collect())
+    diff_words_by(old, new, |text| split_words(text).collect())
 }
 
-#[cfg(feature = "prettytable-rs")]
-fn color_multilines(color: AnsiColors, s: &str) -> String {
-    collect_strings(s.split('\n').map(|i| i.color(color).to_string())).join("\n")
+/// Diff two strings by words with their trailing punctuation, see [`split_words_punctuated`]
+pub fn diff_words_punctuated<'a>(old: &'a str, new: &'a str) -> InlineChangeset<'a> {
+    diff_words_by(old, new, |text| split_words_punctuated(text).collect())
 }
 
-#[derive(Debug)]
-pub struct ContextConfig<'a> {
-    pub context_size: usize,
-    pub skipping_marker: &'a str,
+/// Diff two strings by tokens from `tokenizer`. Tokens are expected to concatenate back to the
+/// original string, otherwise the formatted diff won't show it.
+pub fn diff_words_by<'a, F>(old: &'a str, new: &'a str, tokenizer: F) -> InlineChangeset<'a>
+where
+    F: Fn(&'a str) -> Vec<&'a str>,
+{
+    InlineChangeset::new(tokenizer(old), tokenizer(new))
 }
 
-/// Container for line-by-line text diff result. Can be pretty-printed by Display trait.
-#[derive(Debug, PartialEq, Eq)]
-pub struct LineChangeset<'a> {
-    old: Vec<&'a str>,
-    new: Vec<&'a str>,
+/// Split string into sentences ending with `.`, `!` or `?` followed by whitespace, keeping the
+/// punctuation and whitespace with the sentence. Abbreviations like "e.g." end a sentence too.
+pub fn split_sentences(text: &str) -> impl Iterator<Item = &str> {
+    let mut rest = text;
+    std::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let mut chars = rest.char_indices().peekable();
+        let mut end = rest.len();
+        while let Some((_, c)) = chars.next() {
+            let at_whitespace = chars.peek().is_some_and(|&(_, next)| next.is_whitespace());
+            if matches!(c, '.' | '!' | '?') && at_whitespace {
+                end = chars
+                    .find(|&(_, c)| !c.is_whitespace())
+                    .map_or(rest.len(), |(index, _)| index);
+                break;
+            }
+        }
+        let (sentence, tail) = rest.split_at(end);
+        rest = tail;
+        Some(sentence)
+    })
+}
 
-    names: Option<(&'a str, &'a str)>,
-    diff_only: bool,
-    show_lines: bool,
-    trim_new_lines: bool,
-    aling_new_lines: bool,
+/// Diff two strings by sentences, see [`split_sentences`]
+pub fn diff_sentences<'a>(old: &'a str, new: &'a str) -> InlineChangeset<'a> {
+    InlineChangeset::new(
+        split_sentences(old).collect(),
+        split_sentences(new).collect(),
+    )
 }
 
-impl<'a> LineChangeset<'a> {
-    pub fn new(old: Vec<&'a str>, new: Vec<&'a str>) -> LineChangeset<'a> {
-        LineChangeset {
-            old,
-            new,
-            names: None,
-            diff_only: false,
-            show_lines: true,
-            trim_new_lines: true,
-            aling_new_lines: false,
+/// Returns minimal `(byte range in old, replacement)` edits transforming `old` into `new`.
+/// Edits are sorted and don't overlap, apply them from last to first to keep ranges valid.
+pub fn buffer_edits(old: &str, new: &str) -> Vec<(Range<usize>, String)> {
+    let mut edits: Vec<(Range<usize>, String)> = Vec::new();
+    let mut pos = 0;
+    for op in diff_chars(old, new).diff() {
+        let (removed, inserted) = match op {
+            basic::DiffOp::Equal(a) => {
+                pos += a.concat().len();
+                continue;
+            }
+            basic::DiffOp::Insert(b) => (0, b.concat()),
+            basic::DiffOp::Remove(a) => (a.concat().len(), String::new()),
+            basic::DiffOp::Replace(a, b) => (a.concat().len(), b.concat()),
+        };
+        let range = pos..pos + removed;
+        pos = range.end;
+        match edits.last_mut() {
+            Some((last, text)) if last.end == range.start => {
+                last.end = range.end;
+                text.push_str(&inserted);
+            }
+            _ => edits.push((range, inserted)),
         }
     }
+    edits
+}
 
-    /// Sets names for side-by-side diff
-    pub fn names(mut self, old: &'a str, new: &'a str) -> Self {
-        self.names = Some((old, new));
-        self
-    }
-    /// Show only differences for side-by-side diff
-    pub fn set_diff_only(mut self, val: bool) -> Self {
-        self.diff_only = val;
-        self
-    }
-    /// Show lines in side-by-side diff
-    pub fn set_show_lines(mut self, val: bool) -> Self {
-        self.show_lines = val;
-        self
-    }
-    /// Trim new lines in side-by-side diff
-    pub fn set_trim_new_lines(mut self, val: bool) -> Self {
-        self.trim_new_lines = val;
-        self
-    }
-    /// Align new lines inside diff
-    pub fn set_align_new_lines(mut self, val: bool) -> Self {
-        self.aling_new_lines = val;
-        self
-    }
-    /// Returns Vec of changes
-    pub fn diff(&self) -> Vec<basic::DiffOp<'a, &str>> {
-        basic::diff(&self.old, &self.new)
-    }
+#[cfg(feature = "ansi")]
+fn color_multilines(color: AnsiColors, s: &str) -> String {
+    collect_strings(s.split('\n').map(|i| i.color(color).to_string())).join("\n")
+}
 
-    #[cfg(feature = "prettytable-rs")]
-    fn prettytable_process(&self, a: &[&str], color: Option<AnsiColors>) -> (String, usize) {
-        let mut start = 0;
-        let mut stop = a.len();
-        if self.trim_new_lines {
-            for (index, element) in a.iter().enumerate() {
-                if !element.is_empty() {
-                    break;
-                }
-                start = index + 1;
+/// Returns old and new lines of `op`
+fn op_sides<'b, T>(op: &basic::DiffOp<'b, T>) -> (&'b [T], &'b [T]) {
+    (op.old_slice(), op.new_slice())
+}
+
+/// Replaces tabs by spaces up to the next multiple of `width` terminal columns, wide
+/// characters count as two columns, `0` keeps tabs
+fn expand_tabs(s: &str, width: usize) -> String {
+    if width == 0 || !s.contains('\t') {
+        return s.to_string();
+    }
+    let mut out = String::with_capacity(s.len());
+    let mut column = 0;
+    for c in s.chars() {
+        match c {
+            '\t' => {
+                let spaces = width - column % width;
+                out.extend(std::iter::repeat(' ').take(spaces));
+                column += spaces;
             }
-            for (index, element) in a.iter().enumerate().rev() {
-                if !element.is_empty() {
-                    stop = index + 1;
-                    break;
-                }
+            '\n' => {
+                out.push(c);
+                column = 0;
+            }
+            c => {
+                out.push(c);
+                column += c.width().unwrap_or(0);
             }
-        }
-        let out = &a[start..stop];
-        if let Some(color) = color {
-            (
-                collect_strings(out.iter().map(|i| (*i).color(color)))
-                    .join("\n")
-                    .replace('\t', "    "),
-                start,
-            )
-        } else {
-            (out.join("\n").replace('\t', "    "), start)
         }
     }
+    out
+}
 
-    #[cfg(feature = "prettytable-rs")]
-    fn prettytable_process_replace(
-        &self,
-        old: &[&str],
-        new: &[&str],
-    ) -> ((String, String), (usize, usize)) {
-        // White is dummy argument
-        let (old, old_offset) = self.prettytable_process(old, None);
-        let (new, new_offset) = self.prettytable_process(new, None);
+/// Splits `s` into ANSI escape sequences (`true`) and visible characters (`false`)
+fn ansi_tokens(s: &str) -> impl Iterator<Item = (&str, bool)> {
+    let mut rest = s;
+    std::iter::from_fn(move || {
+        let c = rest.chars().next()?;
+        let escape = c == '\x1b' && rest[1..].starts_with('[');
+        let len = if escape {
+            rest[2..]
+                .find(|c: char| c.is_ascii_alphabetic())
+                .map_or(rest.len(), |end| end + 3)
+        } else {
+            c.len_utf8()
+        };
+        let (token, tail) = rest.split_at(len);
+        rest = tail;
+        Some((token, escape))
+    })
+}
 
-        let mut old_out = String::new();
-        let mut new_out = String::new();
+/// Terminal columns taken by `s`, escape sequences don't count
+fn ansi_width(s: &str) -> usize {
+    ansi_tokens(s)
+        .filter(|(_, escape)| !escape)
+        .map(|(token, _)| token_width(token))
+        .sum()
+}
 
-        for op in diff_words(&old, &new).diff() {
-            match op {
-                basic::DiffOp::Equal(a) => {
-                    old_out.push_str(&a.join(""));
-                    new_out.push_str(&a.join(""));
-                }
-                basic::DiffOp::Insert(a) => {
-                    new_out.push_str(&color_multilines(Green, &a.join("")));
-                }
-                basic::DiffOp::Remove(a) => {
-                    old_out.push_str(&color_multilines(Red, &a.join("")));
+/// Terminal columns taken by visible token of [`ansi_tokens`]
+fn token_width(token: &str) -> usize {
+    token.chars().map(|c| c.width().unwrap_or(0)).sum()
+}
+
+/// Wraps every line of `s` after `width` terminal columns, escape sequences don't count.
+/// Colors active at a break are reset before it and restored after it.
+#[cfg(feature = "table")]
+fn wrap_ansi(s: &str, width: usize) -> String {
+    let mut out = String::with_capacity(s.len());
+    for (index, line) in s.split('\n').enumerate() {
+        if index > 0 {
+            out.push('\n');
+        }
+        let mut active: Vec<&str> = Vec::new();
+        let mut column = 0;
+        for (token, escape) in ansi_tokens(line) {
+            if escape {
+                // `0` resets everything, `2x`, `39` and `49` end the innermost style
+                match &token[2..token.len() - 1] {
+                    "" | "0" => active.clear(),
+                    code if code == "39"
+                        || code == "49"
+                        || (code.len() == 2 && code.starts_with('2')) =>
+                    {
+                        active.pop();
+                    }
+                    _ => active.push(token),
                 }
-                basic::DiffOp::Replace(a, b) => {
-                    old_out.push_str(&color_multilines(Red, &a.join("")));
-                    new_out.push_str(&color_multilines(Green, &b.join("")));
+            } else {
+                let token_width = token_width(token);
+                if column + token_width > width && column > 0 {
+                    if !active.is_empty() {
+                        out.push_str("\x1b[0m");
+                    }
+                    out.push('\n');
+                    active.iter().for_each(|code| out.push_str(code));
+                    column = 0;
                 }
+                column += token_width;
             }
+            out.push_str(token);
         }
-
-        ((old_out, new_out), (old_offset, new_offset))
     }
+    out
+}
 
-    #[cfg(feature = "prettytable-rs")]
-    fn prettytable_mktable(&self) -> prettytable::Table {
-        let mut table = format_table::new();
-        if let Some((old, new)) = &self.names {
-            let mut header = vec![];
-            if self.show_lines {
-                header.push(Cell::new(""));
-            }
-            header.push(Cell::new(&old.cyan().to_string()));
-            if self.show_lines {
-                header.push(Cell::new(""));
-            }
-            header.push(Cell::new(&new.cyan().to_string()));
-            table.set_titles(Row::new(header));
+/// Cuts every line of `s` longer than `width` terminal columns to `width` columns ending
+/// with `…`, escape sequences are kept so colors still end
+fn truncate_ansi(s: &str, width: usize) -> String {
+    collect_strings(s.split('\n').map(|line| {
+        if ansi_width(line) <= width {
+            return line.to_string();
         }
-        let mut old_lines = 1;
-        let mut new_lines = 1;
-        let mut out: Vec<(usize, String, usize, String)> = Vec::new();
-        for op in &self.diff() {
-            match op {
-                basic::DiffOp::Equal(a) => {
-                    let (old, offset) = self.prettytable_process(a, None);
-                    if !self.diff_only {
-                        out.push((old_lines + offset, old.clone(), new_lines + offset, old));
-                    }
-                    old_lines += a.len();
-                    new_lines += a.len();
-                }
-                basic::DiffOp::Insert(a) => {
-                    let (new, offset) = self.prettytable_process(a, Some(Green));
-                    out.push((old_lines, "".to_string(), new_lines + offset, new));
-                    new_lines += a.len();
-                }
-                basic::DiffOp::Remove(a) => {
-                    let (old, offset) = self.prettytable_process(a, Some(Red));
-                    out.push((old_lines + offset, old, new_lines, "".to_string()));
-                    old_lines += a.len();
-                }
-                basic::DiffOp::Replace(a, b) => {
-                    let ((old, new), (old_offset, new_offset)) =
-                        self.prettytable_process_replace(a, b);
-                    out.push((old_lines + old_offset, old, new_lines + new_offset, new));
-                    old_lines += a.len();
-                    new_lines += b.len();
+        let mut out = String::with_capacity(line.len());
+        let mut column = 0;
+        let mut cut = false;
+        for (token, escape) in ansi_tokens(line) {
+            if escape {
+                out.push_str(token);
+            } else if !cut {
+                if column + token_width(token) + 1 > width {
+                    out.push('…');
+                    cut = true;
+                } else {
+                    column += token_width(token);
+                    out.push_str(token);
                 }
-            };
-        }
-        for (old_lines, old, new_lines, new) in out {
-            if self.trim_new_lines && old.trim() == "" && new.trim() == "" {
-                continue;
             }
-            if self.show_lines {
-                table.add_row(row![old_lines, old, new_lines, new]);
-            } else {
-                table.add_row(row![old, new]);
+        }
+        out
+    }))
+    .join("\n")
+}
+
+/// Merges `Equal` runs shorter than `min_len` between two changes of `ops` (a diff of `old` and
+/// `new`) with the changes around them
+fn absorb_short_equal<'a, T>(
+    old: &'a [T],
+    new: &'a [T],
+    ops: &[basic::DiffOp<T>],
+    min_len: usize,
+) -> Vec<basic::DiffOp<'a, T>> {
+    let change = |old: &'a [T], new: &'a [T]| match (old.is_empty(), new.is_empty()) {
+        (true, _) => basic::DiffOp::Insert(new),
+        (_, true) => basic::DiffOp::Remove(old),
+        _ => basic::DiffOp::Replace(old, new),
+    };
+    let mut out = Vec::new();
+    // Start of the change being merged in `old` and `new`
+    let mut pending: Option<(usize, usize)> = None;
+    let (mut i, mut j) = (0, 0);
+    for (index, op) in ops.iter().enumerate() {
+        let absorbed =
+            op.is_equal() && op.old_len() < min_len && pending.is_some() && index + 1 < ops.len();
+        if op.is_change() || absorbed {
+            pending.get_or_insert((i, j));
+        } else {
+            if let Some((start_i, start_j)) = pending.take() {
+                out.push(change(&old[start_i..i], &new[start_j..j]));
             }
+            out.push(basic::DiffOp::Equal(&old[i..i + op.old_len()]));
         }
-        table
+        i += op.old_len();
+        j += op.new_len();
     }
-
-    #[cfg(feature = "prettytable-rs")]
-    /// Prints side-by-side diff in table
-    pub fn prettytable(&self) {
-        let table = self.prettytable_mktable();
-        table.printstd();
+    if let Some((start_i, start_j)) = pending {
+        out.push(change(&old[start_i..], &new[start_j..]));
     }
+    out
+}
 
-    #[cfg(feature = "prettytable-rs")]
-    /// Write side-by-side diff in table to any Writer.
-    pub fn write_prettytable<W>(&self, f: &mut W) -> std::io::Result<usize>
-    where
-        W: std::io::Write + std::io::IsTerminal,
-    {
-        let table = self.prettytable_mktable();
-        table.print(f)
+/// Escapes `&`, `<`, `>` and `"` for HTML output
+fn html_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
     }
+    out
+}
 
-    fn remove_color(&self, a: &str) -> String {
-        a.red().strikethrough().to_string()
-    }
+/// Default CSS for [`LineChangeset::to_html`]
+pub const DEFAULT_STYLESHEET: &str = "\
+table.diff { border-collapse: collapse; font-family: monospace; }
+table.diff td { white-space: pre-wrap; vertical-align: top; padding: 0 0.5em; }
+table.diff td.diff-lineno { color: #888; text-align: right; user-select: none; }
+td.diff-insert { background: #e6ffec; }
+td.diff-remove { background: #ffebe9; }
+td.diff-fold { color: #888; text-align: center; }
+span.diff-insert { background: #abf2bc; }
+span.diff-remove { background: #ffc0c0; }
+";
 
-    fn insert_color(&self, a: &str) -> String {
-        a.green().to_string()
-    }
+/// How side-by-side cells are marked up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Markup {
+    Ansi,
+    Html,
+}
 
-    /// Returns formatted string with colors
-    pub fn format(&self) -> String {
-        self.format_with_context(None, false)
+impl Markup {
+    /// Unchanged text
+    fn plain(self, s: &str) -> String {
+        match self {
+            Markup::Ansi => s.to_string(),
+            Markup::Html => html_escape(s),
+        }
     }
+}
 
-    /// Formats lines in DiffOp::Equal
-    fn format_equal(
-        &self,
-        lines: &[&str],
-        display_line_numbers: bool,
-        prefix_size: usize,
-        line_counter: &mut usize,
-    ) -> Option<String> {
-        lines
-            .iter()
-            .map(|line| {
-                let res = if display_line_numbers {
-                    format!("{} ", *line_counter)
-                        .pad_to_width_with_alignment(prefix_size, Alignment::Right)
-                        + line
-                } else {
-                    "".pad_to_width(prefix_size) + line
-                };
-                *line_counter += 1;
-                res
-            })
-            .reduce(|acc, line| acc + "\n" + &line)
+/// When output is colored, output is never colored without the `ansi` feature.
+/// Changesets start with the [`Default`], which is [`ColorMode::Never`] if `NO_COLOR` is set
+/// and [`ColorMode::Always`] otherwise
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorMode {
+    Always,
+    Never,
+    /// Only if stdout (or the writer of [`LineChangeset::write_prettytable`]) is a terminal
+    /// and `NO_COLOR` isn't set
+    Auto,
+}
+
+impl Default for ColorMode {
+    fn default() -> Self {
+        // Tests expect colors whatever the environment they run in
+        if !cfg!(test) && no_color(std::env::var_os("NO_COLOR")) {
+            ColorMode::Never
+        } else {
+            ColorMode::Always
+        }
     }
+}
 
-    /// Formats lines in DiffOp::Remove
-    fn format_remove(
-        &self,
-        lines: &[&str],
-        display_line_numbers: bool,
-        prefix_size: usize,
-        line_counter: &mut usize,
-    ) -> String {
-        lines
-            .iter()
-            .map(|line| {
-                let res = if display_line_numbers {
-                    format!("{} ", *line_counter)
-                        .pad_to_width_with_alignment(prefix_size, Alignment::Right)
-                        + &self.remove_color(line)
-                } else {
-                    "".pad_to_width(prefix_size) + &self.remove_color(line)
-                };
-                *line_counter += 1;
-                res
-            })
-            .reduce(|acc, line| acc + "\n" + &line)
-            .unwrap()
+/// Whether `NO_COLOR` with value `val` disables colors, an empty value doesn't
+fn no_color(val: Option<std::ffi::OsString>) -> bool {
+    val.is_some_and(|val| !val.is_empty())
+}
+
+impl ColorMode {
+    /// Always false without the `ansi` feature
+    fn enabled(self) -> bool {
+        cfg!(feature = "ansi") && self.enabled_for(std::io::stdout().is_terminal())
     }
 
-    /// Formats lines in DiffOp::Insert
-    fn format_insert(&self, lines: &[&str], prefix_size: usize) -> String {
-        lines
-            .iter()
-            .map(|line| "".pad_to_width(prefix_size) + &self.insert_color(line))
-            .reduce(|acc, line| acc + "\n" + &line)
-            .unwrap()
+    fn enabled_for(self, is_terminal: bool) -> bool {
+        match self {
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+            ColorMode::Auto => is_terminal && !no_color(std::env::var_os("NO_COLOR")),
+        }
     }
+}
 
-    /// Returns formatted string with colors.
-    /// May omit identical lines, if `context_size` is `Some(k)`.
-    /// In this case, only print identical lines if they are within `k` lines
-    /// of a changed line (as in `diff -C`).
-    pub fn format_with_context(
-        &self,
-        context_config: Option<ContextConfig>,
-        display_line_numbers: bool,
-    ) -> String {
-        let line_number_size = if display_line_numbers {
-            (self.old.len() as f64).log10().ceil() as usize
-        } else {
-            0
-        };
-        let skipping_marker_size = if let Some(ContextConfig {
-            skipping_marker, ..
-        }) = context_config
-        {
-            skipping_marker.len()
-        } else {
-            0
-        };
-        let prefix_size = max(line_number_size, skipping_marker_size) + 1;
+/// Style of lines, compares like [`Style`] which only lacks an `Eq` impl
+#[cfg(feature = "ansi")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct LineStyle(Style);
 
-        let mut next_line = 1;
+#[cfg(feature = "ansi")]
+impl Eq for LineStyle {}
 
-        let mut diff = self.diff().into_iter().peekable();
-        let mut out: Vec<String> = Vec::with_capacity(diff.len());
-        let mut at_beginning = true;
-        while let Some(op) = diff.next() {
-            match op {
-                basic::DiffOp::Equal(a) => match context_config {
-                    None => out.push(a.join("\n")),
-                    Some(ContextConfig {
-                        context_size,
-                        skipping_marker,
-                    }) => {
-                        let mut lines = a;
-                        if !at_beginning {
-                            let upper_bound = min(context_size, lines.len());
-                            if let Some(newlines) = self.format_equal(
-                                &lines[..upper_bound],
-                                display_line_numbers,
-                                prefix_size,
-                                &mut next_line,
-                            ) {
-                                out.push(newlines)
-                            }
-                            lines = &lines[upper_bound..];
-                        }
-                        if lines.is_empty() {
-                            continue;
-                        }
-                        let lower_bound = if lines.len() > context_size {
-                            lines.len() - context_size
-                        } else {
-                            0
-                        };
-                        if lower_bound > 0 {
-                            out.push(skipping_marker.to_string());
-                            next_line += lower_bound
-                        }
-                        if diff.peek().is_none() {
-                            continue;
-                        }
-                        if let Some(newlines) = self.format_equal(
-                            &lines[lower_bound..],
-                            display_line_numbers,
-                            prefix_size,
-                            &mut next_line,
-                        ) {
-                            out.push(newlines)
-                        }
-                    }
-                },
-                basic::DiffOp::Insert(a) => out.push(self.format_insert(a, prefix_size)),
-                basic::DiffOp::Remove(a) => out.push(self.format_remove(
-                    a,
-                    display_line_numbers,
-                    prefix_size,
-                    &mut next_line,
-                )),
-                basic::DiffOp::Replace(a, b) => {
-                    out.push(self.format_remove(
-                        a,
-                        display_line_numbers,
-                        prefix_size,
-                        &mut next_line,
-                    ));
-                    out.push(self.format_insert(b, prefix_size));
-                }
+/// Side of a change to highlight
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Change {
+    Insert,
+    Remove,
+}
+
+/// Row of side-by-side diff with CSS classes of its cells
+#[derive(Debug)]
+struct SideBySideRow {
+    old_line: usize,
+    old: String,
+    old_class: &'static str,
+    new_line: usize,
+    new: String,
+    new_class: &'static str,
+}
+
+/// Semantic classification of a single change, see [`LineChangeset::classify`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// Lines appear only in the new text
+    Add,
+    /// Lines appear only in the old text
+    Remove,
+    /// Lines changed in place
+    Modify,
+    /// Lines removed in one place and inserted unchanged in another
+    Move,
+    /// File removed under one path and added with the same content under another, only
+    /// reported by `fs::DirDiff::classify`
+    Rename,
+}
+
+/// Kind of a span of [`InlineChangeset::spans`] and [`LineChangeset::spans`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeTag {
+    /// Text of both inputs
+    Equal,
+    /// Text only in the new input
+    Insert,
+    /// Text only in the old input
+    Remove,
+}
+
+/// Flattens `diff` into tagged tokens, with `separator` between all tokens.
+/// Replaces become their removed tokens followed by their inserted ones.
+fn tagged_spans<'b>(
+    diff: &[basic::DiffOp<'b, &'b str>],
+    separator: &'b str,
+) -> Vec<(ChangeTag, &'b str)> {
+    let mut out = Vec::new();
+    let mut push = |tag: ChangeTag, tokens: &[&'b str]| {
+        for (index, token) in tokens.iter().enumerate() {
+            if index > 0 && !separator.is_empty() {
+                out.push((tag, separator));
+            }
+            out.push((tag, *token));
+        }
+    };
+    for (index, op) in diff.iter().enumerate() {
+        if index > 0 && !separator.is_empty() {
+            push(ChangeTag::Equal, &[separator]);
+        }
+        match op {
+            basic::DiffOp::Equal(a) => push(ChangeTag::Equal, a),
+            basic::DiffOp::Insert(b) => push(ChangeTag::Insert, b),
+            basic::DiffOp::Remove(a) => push(ChangeTag::Remove, a),
+            basic::DiffOp::Replace(a, b) => {
+                push(ChangeTag::Remove, a);
+                push(ChangeTag::Insert, b);
             }
-            at_beginning = false;
         }
-        out.join("\n")
     }
+    out
 }
 
-impl<'a> fmt::Display for LineChangeset<'a> {
-    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        write!(formatter, "{}", self.format())
-    }
+const NO_NEWLINE_MARKER: &str = "\\ No newline at end of file";
+
+#[derive(Debug)]
+pub struct ContextConfig<'a> {
+    pub context_size: usize,
+    pub skipping_marker: &'a str,
 }
 
-pub fn diff_lines<'a>(old: &'a str, new: &'a str) -> LineChangeset<'a> {
-    let old: Vec<&str> = old.lines().collect();
-    let new: Vec<&str> = new.lines().collect();
+/// Size of the side-by-side diff table, see [`LineChangeset::table_metrics`]
+#[cfg(feature = "table")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TableMetrics {
+    /// Table rows without the header, a wrapped row counts once
+    pub rows: usize,
+    /// Terminal columns of the widest line, including borders
+    pub width: usize,
+    /// Printed lines, including borders and the header
+    pub height: usize,
+}
 
-    LineChangeset::new(old, new)
+/// Flags of the gutter column of side-by-side diff, see [`LineChangeset::set_gutter_flags`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GutterFlags {
+    /// Unchanged line, ` ` by default
+    pub equal: char,
+    /// Line only on the left side, `<` by default
+    pub left_only: char,
+    /// Line only on the right side, `>` by default
+    pub right_only: char,
+    /// Line changed on both sides, `|` by default
+    pub changed: char,
 }
 
-fn _test_splitter_basic(text: &str, exp: &[&str]) {
-    let res =
-        collect_strings(split_by_char_fn(text, |c: char| c.is_whitespace()).map(|s| s.to_string()));
-    assert_eq!(res, exp)
+impl Default for GutterFlags {
+    fn default() -> Self {
+        GutterFlags {
+            equal: ' ',
+            left_only: '<',
+            right_only: '>',
+            changed: '|',
+        }
+    }
 }
 
-#[test]
-fn test_splitter() {
-    _test_splitter_basic(
-        "  blah test2 test3  ",
+/// Container for line-by-line text diff result. Can be pretty-printed by Display trait.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineChangeset<'a> {
+    old: Vec<&'a str>,
+    new: Vec<&'a str>,
+
+    names: Option<(&'a str, &'a str)>,
+    diff_only: bool,
+    show_lines: bool,
+    trim_new_lines: bool,
+    aling_new_lines: bool,
+    normalize_line_endings: bool,
+    fold_blank_changes: bool,
+    reversed_columns: bool,
+    shift_changes: basic::ShiftDirection,
+    max_refine_depth: usize,
+    min_inline_match: usize,
+    change_markers: bool,
+    gutter: Option<GutterFlags>,
+    hunk_header_hint: Option<HeaderHint>,
+    fold_marker: Option<FoldMarker>,
+    #[cfg(feature = "ansi")]
+    insert_style: Option<LineStyle>,
+    #[cfg(feature = "ansi")]
+    remove_style: Option<LineStyle>,
+    #[cfg(feature = "ansi")]
+    strikethrough: bool,
+    #[cfg(feature = "ansi")]
+    equal_style: Option<LineStyle>,
+    context: Option<usize>,
+    tab_width: usize,
+    ignore_blank_lines: bool,
+    color: ColorMode,
+    old_newline_at_end: bool,
+    new_newline_at_end: bool,
+    track_final_newline: bool,
+    old_start: usize,
+    new_start: usize,
+    context_lines: Option<usize>,
+    max_column_width: Option<usize>,
+    max_line_length: Option<usize>,
+    ignore_case: bool,
+    line_eq: Option<Comparator>,
+}
+
+impl<'a> LineChangeset<'a> {
+    pub fn new(old: Vec<&'a str>, new: Vec<&'a str>) -> LineChangeset<'a> {
+        LineChangeset {
+            old,
+            new,
+            names: None,
+            diff_only: false,
+            show_lines: true,
+            trim_new_lines: true,
+            aling_new_lines: false,
+            normalize_line_endings: false,
+            fold_blank_changes: false,
+            reversed_columns: false,
+            shift_changes: basic::ShiftDirection::None,
+            max_refine_depth: 1,
+            min_inline_match: 1,
+            change_markers: false,
+            gutter: None,
+            hunk_header_hint: None,
+            fold_marker: None,
+            #[cfg(feature = "ansi")]
+            insert_style: None,
+            #[cfg(feature = "ansi")]
+            remove_style: None,
+            #[cfg(feature = "ansi")]
+            strikethrough: true,
+            #[cfg(feature = "ansi")]
+            equal_style: None,
+            context: None,
+            tab_width: 4,
+            ignore_blank_lines: false,
+            color: ColorMode::default(),
+            old_newline_at_end: true,
+            new_newline_at_end: true,
+            track_final_newline: false,
+            old_start: 1,
+            new_start: 1,
+            context_lines: None,
+            max_column_width: None,
+            max_line_length: None,
+            ignore_case: false,
+            line_eq: None,
+        }
+    }
+
+    /// Sets names for side-by-side diff
+    pub fn names(mut self, old: &'a str, new: &'a str) -> Self {
+        self.names = Some((old, new));
+        self
+    }
+    /// Show only differences for side-by-side diff
+    pub fn set_diff_only(mut self, val: bool) -> Self {
+        self.diff_only = val;
+        self
+    }
+    /// Show lines in side-by-side diff
+    pub fn set_show_lines(mut self, val: bool) -> Self {
+        self.show_lines = val;
+        self
+    }
+    /// Trim new lines in side-by-side diff
+    pub fn set_trim_new_lines(mut self, val: bool) -> Self {
+        self.trim_new_lines = val;
+        self
+    }
+    /// Show replaced lines in side-by-side diff one per row, pairing the n-th old line
+    /// with the n-th new line
+    pub fn set_align_new_lines(mut self, val: bool) -> Self {
+        self.aling_new_lines = val;
+        self
+    }
+    /// Ignore a trailing `\r` when comparing lines, so CRLF and LF lines are equal
+    pub fn set_normalize_line_endings(mut self, val: bool) -> Self {
+        self.normalize_line_endings = val;
+        self
+    }
+    /// Show a run of inserted/removed blank lines as a single line (like `+3 blank lines`)
+    pub fn set_fold_blank_changes(mut self, val: bool) -> Self {
+        self.fold_blank_changes = val;
+        self
+    }
+    /// Show the new text in the left column and the old text in the right one of side-by-side diff
+    pub fn set_reversed_columns(mut self, val: bool) -> Self {
+        self.reversed_columns = val;
+        self
+    }
+    /// Slide ambiguous insertions/removals towards the top or bottom, see [`basic::shift_changes`]
+    pub fn set_shift_changes(mut self, val: basic::ShiftDirection) -> Self {
+        self.shift_changes = val;
+        self
+    }
+    /// How many levels of sub-diffs highlight replaced lines in side-by-side diff:
+    /// 0 highlights whole lines, 1 changed words (default), 2 changed characters within words
+    pub fn set_max_refine_depth(mut self, val: usize) -> Self {
+        self.max_refine_depth = val;
+        self
+    }
+    /// Highlight changed words of replaced lines in side-by-side diff (default), or whole lines.
+    /// Shorthand for [`set_max_refine_depth`](Self::set_max_refine_depth) with 1 or 0
+    pub fn set_inline_highlight(mut self, val: bool) -> Self {
+        self.max_refine_depth = if val {
+            max(self.max_refine_depth, 1)
+        } else {
+            0
+        };
+        self
+    }
+    /// Unchanged runs of fewer tokens than `val` between changes of a replaced line are
+    /// highlighted as part of the change, 1 (keep all runs) by default
+    pub fn set_min_inline_match(mut self, val: usize) -> Self {
+        self.min_inline_match = val;
+        self
+    }
+    /// Prefix every line of [`format`](Self::format) with `+ `, `- `, `~ ` (replaced) or two
+    /// spaces, so changes are visible without colors
+    pub fn set_change_markers(mut self, val: bool) -> Self {
+        self.change_markers = val;
+        self
+    }
+    /// Show a column between both sides of side-by-side diff flagging lines only on the left
+    /// (`<`), only on the right (`>`) or changed on both (`|`), like `sdiff`
+    pub fn set_gutter(mut self, val: bool) -> Self {
+        self.gutter = if val {
+            Some(GutterFlags::default())
+        } else {
+            None
+        };
+        self
+    }
+    /// Show the gutter column of [`set_gutter`](Self::set_gutter) with custom flags
+    pub fn set_gutter_flags(mut self, val: GutterFlags) -> Self {
+        self.gutter = Some(val);
+        self
+    }
+    /// Lines for which `hint` returns true are section headers (e.g. function definitions), the
+    /// last one before a hunk is appended to its `@@` line in unified output, like `diff -p`
+    pub fn set_hunk_header_hint<F>(mut self, hint: F) -> Self
+    where
+        F: Fn(&str) -> bool + Send + Sync + 'static,
+    {
+        self.hunk_header_hint = Some(HeaderHint(Arc::new(hint)));
+        self
+    }
+    /// Text shown instead of collapsed unchanged lines, see [`set_context`](Self::set_context)
+    /// and [`set_context_lines`](Self::set_context_lines). Defaults to `...` in
+    /// [`format`](Self::format) and `⋮` in side-by-side diff
+    pub fn set_fold_marker(self, val: &str) -> Self {
+        let val = val.to_string();
+        self.set_fold_marker_with(move |_| val.clone())
+    }
+    /// Like [`set_fold_marker`](Self::set_fold_marker), but builds the marker from the number of
+    /// collapsed lines, e.g. `|n| format!("@@ skipped {} lines @@", n)`
+    pub fn set_fold_marker_with<F>(mut self, marker: F) -> Self
+    where
+        F: Fn(usize) -> String + Send + Sync + 'static,
+    {
+        self.fold_marker = Some(FoldMarker(Arc::new(marker)));
+        self
+    }
+    /// Style of inserted text, green by default
+    #[cfg(feature = "ansi")]
+    pub fn set_insert_style(mut self, val: Style) -> Self {
+        self.insert_style = Some(LineStyle(val));
+        self
+    }
+    /// Style of removed text, by default red (and struck through in [`format`](Self::format))
+    #[cfg(feature = "ansi")]
+    pub fn set_remove_style(mut self, val: Style) -> Self {
+        self.remove_style = Some(LineStyle(val));
+        self
+    }
+    /// Strike through removed text (default), disable for terminals without strikethrough
+    /// support. Also applies to a custom [`set_remove_style`](Self::set_remove_style)
+    #[cfg(feature = "ansi")]
+    pub fn set_strikethrough(mut self, val: bool) -> Self {
+        self.strikethrough = val;
+        self
+    }
+    /// Style of unchanged lines in [`format`](Self::format) and side-by-side diff, e.g.
+    /// `Style::new().dimmed()`, unstyled by default
+    #[cfg(feature = "ansi")]
+    pub fn set_equal_style(mut self, val: Option<Style>) -> Self {
+        self.equal_style = val.map(LineStyle);
+        self
+    }
+    /// Show only `n` unchanged lines around changes in [`format`](Self::format), longer runs
+    /// are collapsed into `...`. Blank lines count as unchanged lines, `trim_new_lines` only
+    /// applies to side-by-side diff.
+    pub fn set_context(mut self, val: Option<usize>) -> Self {
+        self.context = val;
+        self
+    }
+    /// Expand tabs to the next multiple of `val` columns in side-by-side diff (4 by default),
+    /// 0 leaves tabs untouched
+    pub fn set_tab_width(mut self, val: usize) -> Self {
+        self.tab_width = val;
+        self
+    }
+    /// Don't count inserted or removed runs of blank (empty or whitespace-only) lines as changes:
+    /// they are shown uncolored and left out of [`stats`](Self::stats), [`classify`](Self::classify)
+    /// and hunks, but stay in [`diff`](Self::diff) so both texts can still be rebuilt from it
+    pub fn set_ignore_blank_lines(mut self, val: bool) -> Self {
+        self.ignore_blank_lines = val;
+        self
+    }
+    /// Use colors in output (default unless `NO_COLOR` is set), without colors
+    /// [`format`](Self::format) marks changed lines with `-`/`+`
+    pub fn set_color(mut self, val: bool) -> Self {
+        self.color = if val {
+            ColorMode::Always
+        } else {
+            ColorMode::Never
+        };
+        self
+    }
+    /// When to use colors, see [`ColorMode`]
+    pub fn set_color_mode(mut self, val: ColorMode) -> Self {
+        self.color = val;
+        self
+    }
+    /// Numbers of the first old and new lines in side-by-side diff (1 and 1 by default),
+    /// useful when diffing fragments of larger files
+    pub fn set_line_offsets(mut self, old_start: usize, new_start: usize) -> Self {
+        self.old_start = old_start;
+        self.new_start = new_start;
+        self
+    }
+    /// Show only `val` unchanged lines around changes in side-by-side diff,
+    /// longer unchanged runs are folded into a `⋮` row
+    pub fn set_context_lines(mut self, val: usize) -> Self {
+        self.context_lines = Some(val);
+        self
+    }
+    /// Wrap lines of side-by-side diff table after `val` characters (not counting colors),
+    /// continuation lines get no line number
+    pub fn set_max_column_width(mut self, val: usize) -> Self {
+        self.max_column_width = Some(val);
+        self
+    }
+    /// Cut lines of side-by-side diff table longer than `val` characters (not counting colors)
+    /// to `val` characters ending with `…`
+    pub fn set_max_line_length(mut self, val: Option<usize>) -> Self {
+        self.max_line_length = val;
+        self
+    }
+    /// Count a missing newline at the end of only one text as a change of its last line in
+    /// [`diff`](Self::diff) and everything built on it. [`format_unified`](Self::format_unified)
+    /// always shows it, with a `\ No newline at end of file` marker
+    pub fn set_track_final_newline(mut self, val: bool) -> Self {
+        self.track_final_newline = val;
+        self
+    }
+    /// Compare lines ignoring case, output still shows the original casing.
+    /// Has no effect with [`set_line_eq`](Self::set_line_eq)
+    pub fn set_ignore_case(mut self, val: bool) -> Self {
+        self.ignore_case = val;
+        self
+    }
+    /// Compare lines with `eq` instead of `==`, output still shows the original lines
+    pub fn set_line_eq(mut self, eq: impl Fn(&str, &str) -> bool + Send + Sync + 'static) -> Self {
+        self.line_eq = Some(Comparator::new(eq));
+        self
+    }
+    /// Compare lines by `key(line)`, output still shows the original lines.
+    /// Shorthand for [`set_line_eq`](Self::set_line_eq)
+    pub fn set_line_key<K, F>(self, key: F) -> Self
+    where
+        K: Eq,
+        F: Fn(&str) -> K + Send + Sync + 'static,
+    {
+        self.set_line_eq(move |a, b| key(a) == key(b))
+    }
+    /// Returns Vec of changes
+    pub fn diff(&self) -> Vec<basic::DiffOp<'a, &str>> {
+        let normalize = |line: &'a str| {
+            if self.normalize_line_endings {
+                line.strip_suffix('\r').unwrap_or(line)
+            } else {
+                line
+            }
+        };
+        let diff = basic::diff_by(&self.old, &self.new, |a, b| match &self.line_eq {
+            Some(line_eq) => line_eq.eq(normalize(a), normalize(b)),
+            None if self.ignore_case => eq_ignore_case(normalize(a), normalize(b)),
+            None => normalize(a) == normalize(b),
+        });
+        let diff = basic::shift_changes(&self.old, &self.new, diff, self.shift_changes);
+        if self.track_final_newline {
+            self.split_missing_newline(diff)
+        } else {
+            diff
+        }
+    }
+
+    /// Returns counts of inserted, removed, replaced, unchanged and moved lines
+    pub fn stats(&self) -> basic::DiffStats {
+        let diff: Vec<_> = self
+            .diff()
+            .into_iter()
+            .filter(|op| !self.is_ignored(op))
+            .collect();
+        basic::stats(&diff)
+    }
+
+    /// [`stats`](Self::stats) as a colored summary line with the number of hunks of
+    /// [`format_unified`](Self::format_unified) with `context` lines, like
+    /// `1 file changed, 2 hunks, +3 -1` (`0 files changed` for identical texts), see [`basic::DiffStats::colored_summary_with`]
+    #[cfg(feature = "ansi")]
+    pub fn colored_summary(&self, context: usize) -> String {
+        let diff = self.split_missing_newline(self.diff());
+        let hunks = self.changed_hunks(&diff, context).len();
+        self.stats()
+            .colored_summary_with(usize::from(self.has_changes()), hunks)
+    }
+
+    /// Whether `op` only inserts or removes blank lines and `ignore_blank_lines` is set
+    fn is_ignored(&self, op: &basic::DiffOp<&str>) -> bool {
+        let blank = |lines: &[&str]| lines.iter().all(|line| line.trim().is_empty());
+        self.ignore_blank_lines
+            && match op {
+                basic::DiffOp::Equal(_) => false,
+                basic::DiffOp::Insert(b) => blank(b),
+                basic::DiffOp::Remove(a) => blank(a),
+                basic::DiffOp::Replace(a, b) => blank(a) && blank(b),
+            }
+    }
+
+    /// Drops hunks which only contain ignored changes
+    fn changed_hunks<'b>(
+        &self,
+        diff: &[basic::DiffOp<'b, &'a str>],
+        context: usize,
+    ) -> Vec<basic::Hunk<'b, &'a str>> {
+        let mut hunks = basic::into_hunks(diff, context);
+        hunks.retain(|hunk| {
+            hunk.ops
+                .iter()
+                .any(|op| !matches!(op, basic::DiffOp::Equal(_)) && !self.is_ignored(op))
+        });
+        hunks
+    }
+
+    /// Similarity of both texts from 0.0 to 1.0, see [`basic::ratio`]
+    pub fn ratio(&self) -> f64 {
+        basic::ops_ratio(&self.diff())
+    }
+
+    /// Whether the texts differ, ignored changes don't count. Identical texts aren't diffed
+    pub fn has_changes(&self) -> bool {
+        let newline_changed =
+            self.track_final_newline && self.old_newline_at_end != self.new_newline_at_end;
+        (self.old != self.new || newline_changed)
+            && self
+                .diff()
+                .iter()
+                .any(|op| op.is_change() && !self.is_ignored(op))
+    }
+
+    /// Returns every line of the diff without its newline, tagged by change, for renderers with
+    /// their own styling. Replaced lines are removed lines followed by inserted ones.
+    pub fn spans(&self) -> Vec<(ChangeTag, &str)> {
+        tagged_spans(&self.diff(), "")
+    }
+
+    /// Classifies every change (non-equal op of [`diff`](Self::diff)), in order.
+    /// Removed and inserted blocks with identical content are reported as [`ChangeKind::Move`].
+    pub fn classify(&self) -> Vec<ChangeKind> {
+        let diff = self.diff();
+        let moves = basic::detect_moves(&diff);
+        let is_move = |index| moves.iter().any(|&(r, i)| r == index || i == index);
+        diff.iter()
+            .enumerate()
+            .filter_map(|(index, op)| match op {
+                basic::DiffOp::Equal(_) => None,
+                _ if self.is_ignored(op) => None,
+                _ if is_move(index) => Some(ChangeKind::Move),
+                basic::DiffOp::Insert(_) => Some(ChangeKind::Add),
+                basic::DiffOp::Remove(_) => Some(ChangeKind::Remove),
+                basic::DiffOp::Replace(..) => Some(ChangeKind::Modify),
+            })
+            .collect()
+    }
+
+    fn prettytable_process(
+        &self,
+        a: &[&str],
+        change: Option<Change>,
+        markup: Markup,
+    ) -> (String, usize) {
+        let mut start = 0;
+        let mut stop = a.len();
+        if self.trim_new_lines {
+            for (index, element) in a.iter().enumerate() {
+                if !element.is_empty() {
+                    break;
+                }
+                start = index + 1;
+            }
+            for (index, element) in a.iter().enumerate().rev() {
+                if !element.is_empty() {
+                    stop = index + 1;
+                    break;
+                }
+            }
+        }
+        let out = expand_tabs(&a[start..stop].join("\n"), self.tab_width);
+        match (markup, change) {
+            (Markup::Ansi, Some(change)) => (self.color_lines(change, &out), start),
+            _ => (markup.plain(&out), start),
+        }
+    }
+
+    fn prettytable_process_replace(
+        &self,
+        old: &[&str],
+        new: &[&str],
+        markup: Markup,
+    ) -> ((String, String), (usize, usize)) {
+        let (old, old_offset) = self.prettytable_process(old, None, Markup::Ansi);
+        let (new, new_offset) = self.prettytable_process(new, None, Markup::Ansi);
+
+        (self.refine(&old, &new, 0, markup), (old_offset, new_offset))
+    }
+
+    /// Returns old and new text of every replaced block of [`diff`](Self::diff), colored with the
+    /// word-level highlighting of side-by-side diff, for custom renderers
+    pub fn inline_replace_diff(&self) -> Vec<(String, String)> {
+        self.diff()
+            .iter()
+            .filter(|op| !self.is_ignored(op))
+            .filter_map(|op| match op {
+                basic::DiffOp::Replace(a, b) => {
+                    Some(self.prettytable_process_replace(a, b, Markup::Ansi).0)
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Highlights differences between `old` and `new`, diffing replaced parts again with
+    /// finer tokens (words, then chars) until `max_refine_depth` levels are reached
+    fn refine(&self, old: &str, new: &str, level: usize, markup: Markup) -> (String, String) {
+        let tokenizers: [fn(&str) -> Vec<&str>; 2] = [
+            |s| split_words(s).collect(),
+            |s| s.split("").filter(|i| !i.is_empty()).collect(),
+        ];
+        if level >= self.max_refine_depth || level >= tokenizers.len() {
+            return (
+                self.paint(markup, Change::Remove, old),
+                self.paint(markup, Change::Insert, new),
+            );
+        }
+        let mut old_out = String::new();
+        let mut new_out = String::new();
+
+        let tokenize = tokenizers[level];
+        let (old_tokens, new_tokens) = (tokenize(old), tokenize(new));
+        let tokens = InlineChangeset::new(old_tokens.clone(), new_tokens.clone());
+        let ops = tokens.diff();
+        for op in absorb_short_equal(&old_tokens, &new_tokens, &ops, self.min_inline_match) {
+            match op {
+                basic::DiffOp::Equal(a) => {
+                    old_out.push_str(&markup.plain(&a.join("")));
+                    new_out.push_str(&markup.plain(&a.join("")));
+                }
+                basic::DiffOp::Insert(a) => {
+                    new_out.push_str(&self.paint(markup, Change::Insert, &a.join("")));
+                }
+                basic::DiffOp::Remove(a) => {
+                    old_out.push_str(&self.paint(markup, Change::Remove, &a.join("")));
+                }
+                basic::DiffOp::Replace(a, b) => {
+                    let (old, new) = self.refine(&a.join(""), &b.join(""), level + 1, markup);
+                    old_out.push_str(&old);
+                    new_out.push_str(&new);
+                }
+            }
+        }
+
+        (old_out, new_out)
+    }
+
+    /// Rows of side-by-side diff in display order, shared by table and HTML output
+    fn side_by_side_rows(&self, markup: Markup) -> Vec<SideBySideRow> {
+        let mut old_lines = self.old_start;
+        let mut new_lines = self.new_start;
+        let mut out = Vec::new();
+        let mut row = |old_line, old, old_class, new_line, new, new_class| {
+            out.push(SideBySideRow {
+                old_line,
+                old,
+                old_class,
+                new_line,
+                new,
+                new_class,
+            })
+        };
+        let diff = self.diff();
+        for (index, op) in diff.iter().enumerate() {
+            match op {
+                op if self.is_ignored(op) => {
+                    let (a, b) = op_sides(op);
+                    let (old, old_offset) = self.prettytable_process(a, None, markup);
+                    let (new, new_offset) = self.prettytable_process(b, None, markup);
+                    if !self.diff_only {
+                        row(
+                            old_lines + old_offset,
+                            old,
+                            "diff-equal",
+                            new_lines + new_offset,
+                            new,
+                            "diff-equal",
+                        );
+                    }
+                    old_lines += a.len();
+                    new_lines += b.len();
+                }
+                basic::DiffOp::Equal(a) => {
+                    let parts = if self.diff_only {
+                        vec![]
+                    } else {
+                        self.fold_equal(a.len(), index == 0, index + 1 == diff.len())
+                    };
+                    for part in parts.iter().cloned() {
+                        let range = match part {
+                            Some(range) => range,
+                            None => {
+                                let shown: usize = parts.iter().flatten().map(Range::len).sum();
+                                let fold = || self.fold_text(a.len() - shown, "⋮");
+                                row(
+                                    old_lines,
+                                    fold(),
+                                    "diff-fold",
+                                    new_lines,
+                                    fold(),
+                                    "diff-fold",
+                                );
+                                continue;
+                            }
+                        };
+                        let (old, offset) =
+                            self.prettytable_process(&a[range.clone()], None, markup);
+                        row(
+                            old_lines + range.start + offset,
+                            old.clone(),
+                            "diff-equal",
+                            new_lines + range.start + offset,
+                            old,
+                            "diff-equal",
+                        );
+                    }
+                    old_lines += a.len();
+                    new_lines += a.len();
+                }
+                basic::DiffOp::Insert(a) => {
+                    let (new, offset) = self.prettytable_process(a, Some(Change::Insert), markup);
+                    row(
+                        old_lines,
+                        "".to_string(),
+                        "",
+                        new_lines + offset,
+                        new,
+                        "diff-insert",
+                    );
+                    new_lines += a.len();
+                }
+                basic::DiffOp::Remove(a) => {
+                    let (old, offset) = self.prettytable_process(a, Some(Change::Remove), markup);
+                    row(
+                        old_lines + offset,
+                        old,
+                        "diff-remove",
+                        new_lines,
+                        "".to_string(),
+                        "",
+                    );
+                    old_lines += a.len();
+                }
+                basic::DiffOp::Replace(a, b) if self.aling_new_lines => {
+                    // One row per line, the shorter side is padded with empty cells
+                    for i in 0..max(a.len(), b.len()) {
+                        let (old_line, new_line) =
+                            (old_lines + min(i, a.len()), new_lines + min(i, b.len()));
+                        let (old, new) = match (a.get(i), b.get(i)) {
+                            (Some(old), Some(new)) => {
+                                self.prettytable_process_replace(&[old], &[new], markup).0
+                            }
+                            (Some(old), None) => (
+                                self.prettytable_process(&[old], Some(Change::Remove), markup)
+                                    .0,
+                                String::new(),
+                            ),
+                            (None, Some(new)) => (
+                                String::new(),
+                                self.prettytable_process(&[new], Some(Change::Insert), markup)
+                                    .0,
+                            ),
+                            (None, None) => unreachable!(),
+                        };
+                        let old_class = if i < a.len() { "diff-remove" } else { "" };
+                        let new_class = if i < b.len() { "diff-insert" } else { "" };
+                        row(old_line, old, old_class, new_line, new, new_class);
+                    }
+                    old_lines += a.len();
+                    new_lines += b.len();
+                }
+                basic::DiffOp::Replace(a, b) => {
+                    let ((old, new), (old_offset, new_offset)) =
+                        self.prettytable_process_replace(a, b, markup);
+                    row(
+                        old_lines + old_offset,
+                        old,
+                        "diff-remove",
+                        new_lines + new_offset,
+                        new,
+                        "diff-insert",
+                    );
+                    old_lines += a.len();
+                    new_lines += b.len();
+                }
+            };
+        }
+        out.into_iter()
+            .filter(|row| !(self.trim_new_lines && row.old.trim() == "" && row.new.trim() == ""))
+            .map(|row| {
+                if self.reversed_columns {
+                    SideBySideRow {
+                        old_line: row.new_line,
+                        old: row.new,
+                        old_class: row.new_class,
+                        new_line: row.old_line,
+                        new: row.old,
+                        new_class: row.old_class,
+                    }
+                } else {
+                    row
+                }
+            })
+            .collect()
+    }
+
+    /// Splits an unchanged run of `len` lines into ranges to show and folds (`None`)
+    /// according to `context_lines`, `first` and `last` runs only keep lines towards changes
+    fn fold_equal(&self, len: usize, first: bool, last: bool) -> Vec<Option<Range<usize>>> {
+        let context = match self.context_lines {
+            Some(context) => context,
+            None => return vec![Some(0..len)],
+        };
+        let head = if first { 0 } else { min(context, len) };
+        let tail = if last { 0 } else { min(context, len - head) };
+        if head + tail >= len {
+            return vec![Some(0..len)];
+        }
+        vec![Some(0..head), None, Some(len - tail..len)]
+            .into_iter()
+            .filter(|part| part.as_ref().map_or(true, |range| !range.is_empty()))
+            .collect()
+    }
+
+    /// Returns the marker of `count` collapsed lines, `default` without a custom fold marker
+    fn fold_text(&self, count: usize, default: &str) -> String {
+        match &self.fold_marker {
+            Some(FoldMarker(marker)) => marker(count),
+            None => default.to_string(),
+        }
+    }
+
+    /// Returns names for side-by-side headers, swapped with reversed columns
+    fn side_by_side_names(&self) -> Option<(&'a str, &'a str)> {
+        self.names.map(|(old, new)| {
+            if self.reversed_columns {
+                (new, old)
+            } else {
+                (old, new)
+            }
+        })
+    }
+
+    #[cfg(feature = "table")]
+    fn prettytable_mktable(&self) -> prettytable::Table {
+        let mut table = format_table::new();
+        if let Some((old, new)) = self.side_by_side_names() {
+            let mut header = vec![];
+            if self.show_lines {
+                header.push(Cell::new(""));
+            }
+            header.push(Cell::new(&self.header_color(old)));
+            if self.gutter.is_some() {
+                header.push(Cell::new(""));
+            }
+            if self.show_lines {
+                header.push(Cell::new(""));
+            }
+            header.push(Cell::new(&self.header_color(new)));
+            table.set_titles(Row::new(header));
+        }
+        for mut row in self.side_by_side_rows(Markup::Ansi) {
+            if row.old_class == "diff-equal" {
+                row.old = self.equal_color(row.old);
+                row.new = self.equal_color(row.new);
+            }
+            if let Some(length) = self.max_line_length.filter(|&length| length > 0) {
+                row.old = truncate_ansi(&row.old, length);
+                row.new = truncate_ansi(&row.new, length);
+            }
+            if let Some(width) = self.max_column_width.filter(|&width| width > 0) {
+                row.old = wrap_ansi(&row.old, width);
+                row.new = wrap_ansi(&row.new, width);
+            }
+            let fold = row.old_class == "diff-fold";
+            let mut cells = Vec::with_capacity(5);
+            if self.show_lines {
+                cells.push(if fold {
+                    row.old.clone()
+                } else {
+                    row.old_line.to_string()
+                });
+            }
+            cells.push(row.old.clone());
+            if let Some(flags) = self.gutter {
+                let changed = |class| class == "diff-insert" || class == "diff-remove";
+                let flag = match (changed(row.old_class), changed(row.new_class)) {
+                    (true, true) => flags.changed,
+                    (true, false) => flags.left_only,
+                    (false, true) => flags.right_only,
+                    (false, false) => flags.equal,
+                };
+                cells.push(flag.to_string());
+            }
+            if self.show_lines {
+                cells.push(if fold {
+                    row.new.clone()
+                } else {
+                    row.new_line.to_string()
+                });
+            }
+            cells.push(row.new);
+            table.add_row(Row::new(cells.iter().map(|cell| Cell::new(cell)).collect()));
+        }
+        table
+    }
+
+    /// Returns side-by-side diff as HTML `<table>`, styled by CSS classes
+    /// `diff-insert`, `diff-remove`, `diff-equal` and `diff-lineno`, see [`DEFAULT_STYLESHEET`]
+    pub fn to_html(&self) -> String {
+        let mut out = vec!["<table class=\"diff\">".to_string()];
+        if let Some((old, new)) = self.side_by_side_names() {
+            let lineno = if self.show_lines { "<th></th>" } else { "" };
+            out.push(format!(
+                "<tr>{}<th>{}</th>{}<th>{}</th></tr>",
+                lineno,
+                html_escape(old),
+                lineno,
+                html_escape(new)
+            ));
+        }
+        let cell = |class: &str, content: &str| {
+            if class.is_empty() {
+                format!("<td>{}</td>", content)
+            } else {
+                format!("<td class=\"{}\">{}</td>", class, content)
+            }
+        };
+        for row in self.side_by_side_rows(Markup::Html) {
+            let mut line = "<tr>".to_string();
+            if row.old_class == "diff-fold" {
+                let columns = if self.show_lines { 4 } else { 2 };
+                line.push_str(&format!(
+                    "<td class=\"diff-fold\" colspan=\"{}\">{}</td></tr>",
+                    columns,
+                    html_escape(&row.old)
+                ));
+                out.push(line);
+                continue;
+            }
+            if self.show_lines {
+                line.push_str(&cell("diff-lineno", &row.old_line.to_string()));
+            }
+            line.push_str(&cell(row.old_class, &row.old));
+            if self.show_lines {
+                line.push_str(&cell("diff-lineno", &row.new_line.to_string()));
+            }
+            line.push_str(&cell(row.new_class, &row.new));
+            line.push_str("</tr>");
+            out.push(line);
+        }
+        out.push("</table>".to_string());
+        out.join("\n") + "\n"
+    }
+
+    /// Returns side-by-side diff as two columns of `width` terminal columns divided by `|`,
+    /// without the `prettytable` dependency. Longer lines are cut like with
+    /// [`set_max_line_length`](Self::set_max_line_length)
+    pub fn format_columns(&self, width: usize) -> String {
+        let number_width = max(
+            self.old_start + self.old.len(),
+            self.new_start + self.new.len(),
+        )
+        .to_string()
+        .len();
+        let cell = |text: &str| {
+            let mut text = truncate_ansi(text, width);
+            if text.contains('\x1b') {
+                text.push_str("\x1b[0m");
+            }
+            let padding = width.saturating_sub(ansi_width(&text));
+            text + &" ".repeat(padding)
+        };
+        let number = |line: Option<usize>| {
+            let line = line.map_or(String::new(), |line| line.to_string());
+            format!("{:>width$} ", line, width = number_width)
+        };
+        let mut out = Vec::new();
+        let mut push = |old: &str, old_line, new: &str, new_line| {
+            let mut line = String::new();
+            if self.show_lines {
+                line.push_str(&number(old_line));
+            }
+            line.push_str(&cell(old));
+            line.push_str(" | ");
+            if self.show_lines {
+                line.push_str(&number(new_line));
+            }
+            line.push_str(&cell(new));
+            out.push(line.trim_end().to_string());
+        };
+        if let Some((old, new)) = self.side_by_side_names() {
+            push(&self.header_color(old), None, &self.header_color(new), None);
+        }
+        for mut row in self.side_by_side_rows(Markup::Ansi) {
+            if row.old_class == "diff-fold" {
+                push(&row.old, None, &row.new, None);
+                continue;
+            }
+            if row.old_class == "diff-equal" {
+                row.old = self.equal_color(row.old);
+                row.new = self.equal_color(row.new);
+            }
+            let old: Vec<&str> = row.old.split('\n').collect();
+            let new: Vec<&str> = row.new.split('\n').collect();
+            for index in 0..max(old.len(), new.len()) {
+                let side = |lines: &[&str], class: &str, start: usize| match lines.get(index) {
+                    Some(line) if !class.is_empty() => (line.to_string(), Some(start + index)),
+                    _ => (String::new(), None),
+                };
+                let (old_text, old_line) = side(&old, row.old_class, row.old_line);
+                let (new_text, new_line) = side(&new, row.new_class, row.new_line);
+                push(&old_text, old_line, &new_text, new_line);
+            }
+        }
+        out.iter().map(|line| format!("{}\n", line)).collect()
+    }
+
+    /// Prints side-by-side diff in table
+    #[cfg(feature = "table")]
+    pub fn prettytable(&self) {
+        let _ = self.write_prettytable(&mut std::io::stdout());
+    }
+
+    /// Writes side-by-side diff in table to `out`, colors are kept even if `out` is not a terminal.
+    /// [`ColorMode::Auto`] can't tell whether `out` is a terminal and writes no colors,
+    /// use [`write_prettytable`](Self::write_prettytable) for terminals.
+    #[cfg(feature = "table")]
+    pub fn prettytable_to<W: std::io::Write>(&self, out: &mut W) -> std::io::Result<()> {
+        self.with_color_for(false)
+            .prettytable_mktable()
+            .print(out)
+            .map(|_| ())
+    }
+
+    /// Resolves [`ColorMode::Auto`] for output to a terminal or not
+    #[cfg(feature = "table")]
+    fn with_color_for(&self, is_terminal: bool) -> Cow<'_, Self> {
+        if self.color == ColorMode::Auto {
+            let mut resolved = self.clone();
+            resolved.color = if self.color.enabled_for(is_terminal) {
+                ColorMode::Always
+            } else {
+                ColorMode::Never
+            };
+            Cow::Owned(resolved)
+        } else {
+            Cow::Borrowed(self)
+        }
+    }
+
+    /// Returns side-by-side diff in table, colored unless the color mode is
+    /// [`ColorMode::Never`] or [`ColorMode::Auto`]. Every line ends with a newline
+    /// (`\r\n` on Windows) including the last one
+    #[cfg(feature = "table")]
+    pub fn prettytable_string(&self) -> String {
+        let mut out = Vec::new();
+        self.prettytable_to(&mut out)
+            .expect("writing to Vec<u8> can't fail");
+        String::from_utf8(out).expect("table of valid strings is valid UTF-8")
+    }
+
+    /// Returns the size [`prettytable`](Self::prettytable) output would have, with
+    /// wrapping and truncation applied
+    #[cfg(feature = "table")]
+    pub fn table_metrics(&self) -> TableMetrics {
+        let table = self.prettytable_mktable();
+        let rendered = table.to_string();
+        let width = rendered.lines().map(ansi_width).max().unwrap_or(0);
+        TableMetrics {
+            rows: table.len(),
+            width,
+            height: rendered.lines().count(),
+        }
+    }
+
+    /// Write side-by-side diff in table to any Writer.
+    /// [`ColorMode::Auto`] uses colors if `f` is a terminal.
+    #[cfg(feature = "table")]
+    pub fn write_prettytable<W>(&self, f: &mut W) -> std::io::Result<usize>
+    where
+        W: std::io::Write + std::io::IsTerminal,
+    {
+        let table = self.with_color_for(f.is_terminal()).prettytable_mktable();
+        table.print(f)
+    }
+
+    fn remove_color(&self, a: &str) -> String {
+        #[cfg(feature = "ansi")]
+        if self.color.enabled() {
+            return match self.remove_style {
+                Some(LineStyle(style)) => a.style(self.strip_strikethrough(style)).to_string(),
+                None if self.strikethrough => a.red().strikethrough().to_string(),
+                None => a.red().to_string(),
+            };
+        }
+        a.to_string()
+    }
+
+    /// Styles every line of unchanged `s` with `equal_style`
+    fn equal_color(&self, s: String) -> String {
+        #[cfg(feature = "ansi")]
+        if let Some(LineStyle(style)) = self.equal_style.filter(|_| self.color.enabled()) {
+            return collect_strings(s.split('\n').map(|i| i.style(style).to_string())).join("\n");
+        }
+        s
+    }
+
+    /// Returns `style` without strikethrough if it is disabled
+    #[cfg(feature = "ansi")]
+    fn strip_strikethrough(&self, style: Style) -> Style {
+        if self.strikethrough {
+            style
+        } else {
+            style.remove_effect(Effect::Strikethrough)
+        }
+    }
+
+    fn insert_color(&self, a: &str) -> String {
+        #[cfg(feature = "ansi")]
+        if self.color.enabled() {
+            return match self.insert_style {
+                Some(LineStyle(style)) => a.style(style).to_string(),
+                None => a.green().to_string(),
+            };
+        }
+        a.to_string()
+    }
+
+    fn header_color(&self, name: &str) -> String {
+        #[cfg(feature = "ansi")]
+        if self.color.enabled() {
+            return name.cyan().to_string();
+        }
+        name.to_string()
+    }
+
+    /// Without colors, replaces the trailing space of `prefix` by `sign`
+    fn mark_prefix(&self, mut prefix: String, sign: char) -> String {
+        // Change markers take the place of the sign
+        if self.change_markers && prefix.ends_with(' ') {
+            prefix.pop();
+        } else if !self.color.enabled() && prefix.ends_with(' ') {
+            prefix.pop();
+            prefix.push(sign);
+        }
+        prefix
+    }
+
+    /// Colors every line of `s` with the style of `change` for side-by-side diff
+    #[cfg_attr(not(feature = "ansi"), allow(unused_variables))]
+    fn color_lines(&self, change: Change, s: &str) -> String {
+        #[cfg(feature = "ansi")]
+        if self.color.enabled() {
+            let (style, color) = match change {
+                Change::Insert => (self.insert_style, Green),
+                Change::Remove => (self.remove_style, Red),
+            };
+            return match style {
+                Some(LineStyle(style)) => {
+                    let style = self.strip_strikethrough(style);
+                    collect_strings(s.split('\n').map(|i| i.style(style).to_string())).join("\n")
+                }
+                None => color_multilines(color, s),
+            };
+        }
+        s.to_string()
+    }
+
+    /// Highlights changed text inside a side-by-side cell
+    fn paint(&self, markup: Markup, change: Change, s: &str) -> String {
+        match markup {
+            Markup::Ansi => self.color_lines(change, s),
+            Markup::Html => {
+                let class = match change {
+                    Change::Insert => "diff-insert",
+                    Change::Remove => "diff-remove",
+                };
+                format!("<span class=\"{}\">{}</span>", class, html_escape(s))
+            }
+        }
+    }
+
+    /// Returns formatted string with colors
+    pub fn format(&self) -> String {
+        let context_config = self.context.map(|context_size| ContextConfig {
+            context_size,
+            skipping_marker: "...",
+        });
+        self.format_with_context(context_config, false)
+    }
+
+    /// Writes every line of the diff like [`format`](Self::format) without context, colored
+    /// through `termcolor` which also works on older Windows consoles. Inserted lines are green,
+    /// removed ones red and struck through. Without colors (a `termcolor` writer without color
+    /// support or [`ColorMode::Never`]) changed lines are marked by `+` and `-`
+    #[cfg(feature = "termcolor")]
+    pub fn write_colored<W: termcolor::WriteColor>(&self, w: &mut W) -> io::Result<()> {
+        use termcolor::{Color, ColorSpec};
+
+        let colored = self.color != ColorMode::Never && w.supports_color();
+        #[cfg(feature = "ansi")]
+        let strikethrough = self.strikethrough;
+        #[cfg(not(feature = "ansi"))]
+        let strikethrough = true;
+        let mut insert = ColorSpec::new();
+        insert.set_fg(Some(Color::Green));
+        let mut remove = ColorSpec::new();
+        remove
+            .set_fg(Some(Color::Red))
+            .set_strikethrough(strikethrough);
+
+        // Unchanged lines have no sign, like in `format()`
+        let mut write_lines = |lines: &[&str], sign: Option<char>| {
+            for line in lines {
+                match sign {
+                    None => writeln!(w, "{}", line)?,
+                    Some(sign) if !colored => writeln!(w, "{}{}", sign, line)?,
+                    Some(sign) => {
+                        write!(w, " ")?;
+                        w.set_color(if sign == '+' { &insert } else { &remove })?;
+                        write!(w, "{}", line)?;
+                        w.reset()?;
+                        writeln!(w)?;
+                    }
+                }
+            }
+            Ok::<_, io::Error>(())
+        };
+        for op in self.diff() {
+            match op {
+                basic::DiffOp::Equal(a) => write_lines(a, None)?,
+                op if self.is_ignored(&op) => {
+                    let (a, b) = op_sides(&op);
+                    write_lines(if a.is_empty() { b } else { a }, None)?;
+                }
+                basic::DiffOp::Insert(b) => write_lines(b, Some('+'))?,
+                basic::DiffOp::Remove(a) => write_lines(a, Some('-'))?,
+                basic::DiffOp::Replace(a, b) => {
+                    write_lines(a, Some('-'))?;
+                    write_lines(b, Some('+'))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns names for diff headers, `old` and `new` if not set
+    fn header_names(&self) -> (&'a str, &'a str) {
+        self.names.unwrap_or(("old", "new"))
+    }
+
+    /// Returns diff in "context diff" format (`diff -c`) with `context` lines around changes
+    pub fn context_diff(&self, context: usize) -> String {
+        let diff = self.diff();
+        let hunks = self.changed_hunks(&diff, context);
+        if hunks.is_empty() {
+            return String::new();
+        }
+        let range = |start: usize, len: usize| {
+            if len > 1 {
+                format!("{},{}", start + 1, start + len)
+            } else {
+                format!("{}", start + len)
+            }
+        };
+        let (old_name, new_name) = self.header_names();
+        let mut out = vec![format!("*** {}", old_name), format!("--- {}", new_name)];
+        for hunk in hunks {
+            out.push("***************".to_string());
+            out.push(format!("*** {} ****", range(hunk.old_start, hunk.old_len)));
+            let has = |f: fn(&basic::DiffOp<&str>) -> bool| hunk.ops.iter().any(f);
+            if has(|op| matches!(op, basic::DiffOp::Remove(_) | basic::DiffOp::Replace(..))) {
+                for op in &hunk.ops {
+                    let (marker, lines) = match op {
+                        basic::DiffOp::Equal(a) => ("  ", *a),
+                        basic::DiffOp::Remove(a) => ("- ", *a),
+                        basic::DiffOp::Replace(a, _) => ("! ", *a),
+                        basic::DiffOp::Insert(_) => continue,
+                    };
+                    out.extend(lines.iter().map(|line| format!("{}{}", marker, line)));
+                }
+            }
+            out.push(format!("--- {} ----", range(hunk.new_start, hunk.new_len)));
+            if has(|op| matches!(op, basic::DiffOp::Insert(_) | basic::DiffOp::Replace(..))) {
+                for op in &hunk.ops {
+                    let (marker, lines) = match op {
+                        basic::DiffOp::Equal(a) => ("  ", *a),
+                        basic::DiffOp::Insert(b) => ("+ ", *b),
+                        basic::DiffOp::Replace(_, b) => ("! ", *b),
+                        basic::DiffOp::Remove(_) => continue,
+                    };
+                    out.extend(lines.iter().map(|line| format!("{}{}", marker, line)));
+                }
+            }
+        }
+        out.join("\n") + "\n"
+    }
+
+    /// Returns diff in unified format (`diff -u`) with `context` lines around changes
+    pub fn format_unified(&self, context: usize) -> String {
+        let diff = self.split_missing_newline(self.diff());
+        let hunks = self.changed_hunks(&diff, context);
+        if hunks.is_empty() {
+            return String::new();
+        }
+        let range = |start: usize, len: usize| match len {
+            0 => format!("{},0", start),
+            1 => format!("{}", start + 1),
+            _ => format!("{},{}", start + 1, len),
+        };
+        let (old_name, new_name) = self.header_names();
+        let mut out = vec![format!("--- {}", old_name), format!("+++ {}", new_name)];
+        for hunk in hunks {
+            let section = self.hunk_header_hint.as_ref().and_then(|hint| {
+                self.old[..hunk.old_start]
+                    .iter()
+                    .rev()
+                    .find(|line| (hint.0)(line))
+            });
+            out.push(format!(
+                "@@ -{} +{} @@{}",
+                range(hunk.old_start, hunk.old_len),
+                range(hunk.new_start, hunk.new_len),
+                section.map_or(String::new(), |line| format!(" {}", line))
+            ));
+            let (mut i, mut j) = (hunk.old_start, hunk.new_start);
+            for op in &hunk.ops {
+                let (old_lines, new_lines): (&[&str], &[&str]) = match op {
+                    basic::DiffOp::Equal(a) => {
+                        for line in a.iter() {
+                            out.push(format!(" {}", line));
+                        }
+                        i += a.len();
+                        j += a.len();
+                        if i == self.old.len() && !self.old_newline_at_end {
+                            out.push(NO_NEWLINE_MARKER.to_string());
+                        }
+                        continue;
+                    }
+                    basic::DiffOp::Insert(b) => (&[], b),
+                    basic::DiffOp::Remove(a) => (a, &[]),
+                    basic::DiffOp::Replace(a, b) => (a, b),
+                };
+                out.extend(old_lines.iter().map(|line| format!("-{}", line)));
+                i += old_lines.len();
+                if !old_lines.is_empty() && i == self.old.len() && !self.old_newline_at_end {
+                    out.push(NO_NEWLINE_MARKER.to_string());
+                }
+                out.extend(new_lines.iter().map(|line| format!("+{}", line)));
+                j += new_lines.len();
+                if !new_lines.is_empty() && j == self.new.len() && !self.new_newline_at_end {
+                    out.push(NO_NEWLINE_MARKER.to_string());
+                }
+            }
+        }
+        out.join("\n") + "\n"
+    }
+
+    /// Returns diff as a patch for `git apply`, with `a/old_path` and `b/new_path` headers and
+    /// 3 lines of context. The `index` line has placeholder hashes. Returns an empty string if
+    /// there are no changes.
+    pub fn to_git_patch(&self, old_path: &str, new_path: &str) -> String {
+        let (old_name, new_name) = (format!("a/{}", old_path), format!("b/{}", new_path));
+        let unified = self
+            .with_lines(
+                self.old.clone(),
+                self.new.clone(),
+                Some((&old_name, &new_name)),
+            )
+            .format_unified(3);
+        if unified.is_empty() {
+            return unified;
+        }
+        format!(
+            "diff --git {} {}\nindex 0000000..0000000 100644\n{}",
+            old_name, new_name, unified
+        )
+    }
+
+    /// Returns diff as a Markdown ```` ```diff ```` code block without colors, so GitHub and GitLab
+    /// highlight it. Every body line starts with `+`, `-` or a space, unchanged lines are
+    /// skipped with `diff_only`. Returns an empty string if there are no changes.
+    pub fn to_markdown(&self) -> String {
+        let diff = self.diff();
+        let unchanged =
+            |op: &basic::DiffOp<&str>| matches!(op, basic::DiffOp::Equal(_)) || self.is_ignored(op);
+        if diff.iter().all(unchanged) {
+            return String::new();
+        }
+        let (old_name, new_name) = self.header_names();
+        let mut body = vec![format!("--- {}", old_name), format!("+++ {}", new_name)];
+        for op in &diff {
+            let (old_lines, new_lines) = op_sides(op);
+            if unchanged(op) {
+                if !self.diff_only {
+                    let lines = if old_lines.is_empty() {
+                        new_lines
+                    } else {
+                        old_lines
+                    };
+                    body.extend(lines.iter().map(|line| format!(" {}", line)));
+                }
+                continue;
+            }
+            body.extend(old_lines.iter().map(|line| format!("-{}", line)));
+            body.extend(new_lines.iter().map(|line| format!("+{}", line)));
+        }
+        // The fence must be longer than any backtick run in the diff
+        let longest_run = body
+            .iter()
+            .flat_map(|line| line.split(|c| c != '`'))
+            .map(str::len)
+            .max()
+            .unwrap_or(0);
+        let fence = "`".repeat(max(3, longest_run + 1));
+        format!("{}diff\n{}\n{}\n", fence, body.join("\n"), fence)
+    }
+
+    /// Turns a last line without newline into a change when the other side has the newline
+    fn split_missing_newline<'b>(
+        &'b self,
+        diff: Vec<basic::DiffOp<'b, &'a str>>,
+    ) -> Vec<basic::DiffOp<'b, &'a str>> {
+        if self.old_newline_at_end == self.new_newline_at_end {
+            return diff;
+        }
+        let mut builder = basic::OpsBuilder::new(&self.old, &self.new);
+        let (mut i, mut j) = (0, 0);
+        for op in diff {
+            match op {
+                basic::DiffOp::Equal(a) => {
+                    i += a.len();
+                    j += a.len();
+                    if (i == self.old.len() && !self.old_newline_at_end)
+                        || (j == self.new.len() && !self.new_newline_at_end)
+                    {
+                        builder.equal(a.len() - 1);
+                        builder.change(1, 1);
+                    } else {
+                        builder.equal(a.len());
+                    }
+                }
+                basic::DiffOp::Insert(b) => {
+                    j += b.len();
+                    builder.change(0, b.len());
+                }
+                basic::DiffOp::Remove(a) => {
+                    i += a.len();
+                    builder.change(a.len(), 0);
+                }
+                basic::DiffOp::Replace(a, b) => {
+                    i += a.len();
+                    j += b.len();
+                    builder.change(a.len(), b.len());
+                }
+            }
+        }
+        builder.finish()
+    }
+
+    /// Formats lines in DiffOp::Equal
+    fn format_equal(
+        &self,
+        lines: &[&str],
+        display_line_numbers: bool,
+        prefix_size: usize,
+        line_counter: &mut usize,
+    ) -> Option<String> {
+        lines
+            .iter()
+            .map(|line| {
+                let res = if display_line_numbers {
+                    format!("{} ", *line_counter)
+                        .pad_to_width_with_alignment(prefix_size, Alignment::Right)
+                        + line
+                } else {
+                    "".pad_to_width(prefix_size) + line
+                };
+                *line_counter += 1;
+                res
+            })
+            .reduce(|acc, line| acc + "\n" + &line)
+    }
+
+    /// Formats lines in DiffOp::Remove
+    fn format_remove(
+        &self,
+        lines: &[&str],
+        display_line_numbers: bool,
+        prefix_size: usize,
+        line_counter: &mut usize,
+    ) -> String {
+        self.fold_blank_runs(lines, '-')
+            .iter()
+            .map(|(line, count)| {
+                let prefix = if display_line_numbers {
+                    format!("{} ", *line_counter)
+                        .pad_to_width_with_alignment(prefix_size, Alignment::Right)
+                } else {
+                    "".pad_to_width(prefix_size)
+                };
+                let res = self.mark_prefix(prefix, '-') + &self.remove_color(line);
+                *line_counter += count;
+                res
+            })
+            .reduce(|acc, line| acc + "\n" + &line)
+            .unwrap()
+    }
+
+    /// Formats lines in DiffOp::Insert
+    fn format_insert(&self, lines: &[&str], prefix_size: usize) -> String {
+        self.fold_blank_runs(lines, '+')
+            .iter()
+            .map(|(line, _)| {
+                self.mark_prefix("".pad_to_width(prefix_size), '+') + &self.insert_color(line)
+            })
+            .reduce(|acc, line| acc + "\n" + &line)
+            .unwrap()
+    }
+
+    /// Returns lines to display with the number of source lines each one covers.
+    /// With `fold_blank_changes` a run of blank lines is replaced by a `{sign}N blank lines` line.
+    fn fold_blank_runs<'b>(&self, lines: &[&'b str], sign: char) -> Vec<(Cow<'b, str>, usize)> {
+        let mut out: Vec<(Cow<str>, usize)> = Vec::with_capacity(lines.len());
+        let mut rest = lines;
+        while let Some(line) = rest.first() {
+            let blank = if self.fold_blank_changes {
+                rest.iter()
+                    .take_while(|line| line.trim().is_empty())
+                    .count()
+            } else {
+                0
+            };
+            if blank > 1 {
+                out.push((format!("{}{} blank lines", sign, blank).into(), blank));
+                rest = &rest[blank..];
+            } else {
+                out.push(((*line).into(), 1));
+                rest = &rest[1..];
+            }
+        }
+        out
+    }
+
+    /// Returns formatted string with colors.
+    /// May omit identical lines, if `context_size` is `Some(k)`.
+    /// In this case, only print identical lines if they are within `k` lines
+    /// of a changed line (as in `diff -C`). A marker set with
+    /// [`set_fold_marker`](Self::set_fold_marker) replaces `skipping_marker`.
+    pub fn format_with_context(
+        &self,
+        context_config: Option<ContextConfig>,
+        display_line_numbers: bool,
+    ) -> String {
+        let line_number_size = if display_line_numbers {
+            (self.old.len() as f64).log10().ceil() as usize
+        } else {
+            0
+        };
+        let skipping_marker_size = if let Some(ContextConfig {
+            skipping_marker, ..
+        }) = context_config
+        {
+            skipping_marker.len()
+        } else {
+            0
+        };
+        let prefix_size = max(line_number_size, skipping_marker_size) + 1;
+
+        let mut next_line = 1;
+
+        let mark = |marker: char, lines: String| {
+            if self.change_markers {
+                collect_strings(lines.split('\n').map(|line| format!("{} {}", marker, line)))
+                    .join("\n")
+            } else {
+                lines
+            }
+        };
+
+        let mut diff = self.diff().into_iter().peekable();
+        let mut out: Vec<String> = Vec::with_capacity(diff.len());
+        let mut at_beginning = true;
+        while let Some(op) = diff.next() {
+            match op {
+                basic::DiffOp::Equal(a) => match context_config {
+                    None => out.push(mark(' ', self.equal_color(a.join("\n")))),
+                    Some(ContextConfig {
+                        context_size,
+                        skipping_marker,
+                    }) => {
+                        let mut lines = a;
+                        if !at_beginning {
+                            let upper_bound = min(context_size, lines.len());
+                            if let Some(newlines) = self.format_equal(
+                                &lines[..upper_bound],
+                                display_line_numbers,
+                                prefix_size,
+                                &mut next_line,
+                            ) {
+                                out.push(mark(' ', self.equal_color(newlines)))
+                            }
+                            lines = &lines[upper_bound..];
+                        }
+                        if lines.is_empty() {
+                            continue;
+                        }
+                        let lower_bound = if lines.len() > context_size {
+                            lines.len() - context_size
+                        } else {
+                            0
+                        };
+                        let last = diff.peek().is_none();
+                        if lower_bound > 0 {
+                            let skipped = if last { lines.len() } else { lower_bound };
+                            out.push(mark(' ', self.fold_text(skipped, skipping_marker)));
+                            next_line += lower_bound
+                        }
+                        if last {
+                            continue;
+                        }
+                        if let Some(newlines) = self.format_equal(
+                            &lines[lower_bound..],
+                            display_line_numbers,
+                            prefix_size,
+                            &mut next_line,
+                        ) {
+                            out.push(mark(' ', self.equal_color(newlines)))
+                        }
+                    }
+                },
+                // Shown like unchanged lines, inserted ones without a line number
+                op if self.is_ignored(&op) => {
+                    let (a, b) = op_sides(&op);
+                    let lines = if context_config.is_none() {
+                        Some(if a.is_empty() { b } else { a }.join("\n"))
+                    } else if a.is_empty() {
+                        b.iter()
+                            .map(|line| "".pad_to_width(prefix_size) + line)
+                            .reduce(|acc, line| acc + "\n" + &line)
+                    } else {
+                        self.format_equal(a, display_line_numbers, prefix_size, &mut next_line)
+                    };
+                    out.extend(lines.map(|lines| mark(' ', lines)));
+                }
+                basic::DiffOp::Insert(a) => out.push(mark('+', self.format_insert(a, prefix_size))),
+                basic::DiffOp::Remove(a) => out.push(mark(
+                    '-',
+                    self.format_remove(a, display_line_numbers, prefix_size, &mut next_line),
+                )),
+                basic::DiffOp::Replace(a, b) => {
+                    out.push(mark(
+                        '~',
+                        self.format_remove(a, display_line_numbers, prefix_size, &mut next_line),
+                    ));
+                    out.push(mark('~', self.format_insert(b, prefix_size)));
+                }
+            }
+            at_beginning = false;
+        }
+        out.join("\n")
+    }
+
+    /// Copies the settings into a changeset of other lines
+    fn with_lines<'b>(
+        &self,
+        old: Vec<&'b str>,
+        new: Vec<&'b str>,
+        names: Option<(&'b str, &'b str)>,
+    ) -> LineChangeset<'b> {
+        LineChangeset {
+            old,
+            new,
+            names,
+            diff_only: self.diff_only,
+            show_lines: self.show_lines,
+            trim_new_lines: self.trim_new_lines,
+            aling_new_lines: self.aling_new_lines,
+            normalize_line_endings: self.normalize_line_endings,
+            fold_blank_changes: self.fold_blank_changes,
+            reversed_columns: self.reversed_columns,
+            shift_changes: self.shift_changes,
+            max_refine_depth: self.max_refine_depth,
+            min_inline_match: self.min_inline_match,
+            change_markers: self.change_markers,
+            gutter: self.gutter,
+            hunk_header_hint: self.hunk_header_hint.clone(),
+            fold_marker: self.fold_marker.clone(),
+            #[cfg(feature = "ansi")]
+            insert_style: self.insert_style,
+            #[cfg(feature = "ansi")]
+            remove_style: self.remove_style,
+            #[cfg(feature = "ansi")]
+            strikethrough: self.strikethrough,
+            #[cfg(feature = "ansi")]
+            equal_style: self.equal_style,
+            context: self.context,
+            tab_width: self.tab_width,
+            ignore_blank_lines: self.ignore_blank_lines,
+            color: self.color,
+            old_newline_at_end: self.old_newline_at_end,
+            new_newline_at_end: self.new_newline_at_end,
+            track_final_newline: self.track_final_newline,
+            old_start: self.old_start,
+            new_start: self.new_start,
+            context_lines: self.context_lines,
+            max_column_width: self.max_column_width,
+            max_line_length: self.max_line_length,
+            ignore_case: self.ignore_case,
+            line_eq: self.line_eq.clone(),
+        }
+    }
+
+    /// Copies the lines and names so the changeset no longer borrows its inputs
+    pub fn into_owned(self) -> LineChangesetOwned {
+        LineChangesetOwned {
+            old: collect_strings(self.old.iter()),
+            new: collect_strings(self.new.iter()),
+            names: self
+                .names
+                .map(|(old, new)| (old.to_string(), new.to_string())),
+            settings: self.with_lines(Vec::new(), Vec::new(), None),
+        }
+    }
+}
+
+impl<'a> fmt::Display for LineChangeset<'a> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{}", self.format())
+    }
+}
+
+/// [`LineChangeset`] owning its lines, see [`LineChangeset::into_owned`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineChangesetOwned {
+    old: Vec<String>,
+    new: Vec<String>,
+    names: Option<(String, String)>,
+    settings: LineChangeset<'static>,
+}
+
+impl LineChangesetOwned {
+    /// Borrowing changeset with the same settings, for all other methods
+    pub fn changeset(&self) -> LineChangeset<'_> {
+        self.settings.with_lines(
+            self.old.iter().map(String::as_str).collect(),
+            self.new.iter().map(String::as_str).collect(),
+            self.names
+                .as_ref()
+                .map(|(old, new)| (old.as_str(), new.as_str())),
+        )
+    }
+
+    pub fn format(&self) -> String {
+        self.changeset().format()
+    }
+
+    /// Changes the settings with the builder methods of [`LineChangeset`]
+    pub fn configure<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(LineChangeset<'static>) -> LineChangeset<'static>,
+    {
+        self.settings = f(self.settings);
+        if let Some((old, new)) = self.settings.names.take() {
+            self.names = Some((old.to_string(), new.to_string()));
+        }
+        self
+    }
+}
+
+impl fmt::Display for LineChangesetOwned {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{}", self.format())
+    }
+}
+
+pub fn diff_lines<'a>(old: &'a str, new: &'a str) -> LineChangeset<'a> {
+    let mut changeset = LineChangeset::new(old.lines().collect(), new.lines().collect());
+    changeset.old_newline_at_end = old.is_empty() || old.ends_with('\n');
+    changeset.new_newline_at_end = new.is_empty() || new.ends_with('\n');
+    changeset
+}
+
+/// Diff lines compared by `key(line)`, e.g. with thousands separators removed, output still
+/// shows the original lines. See [`LineChangeset::set_line_key`]
+pub fn diff_lines_by_key<'a, K, F>(old: &'a str, new: &'a str, key: F) -> LineChangeset<'a>
+where
+    K: Eq,
+    F: Fn(&str) -> K + Send + Sync + 'static,
+{
+    diff_lines(old, new).set_line_key(key)
+}
+
+/// Reads both inputs line by line and diffs them like [`diff_lines`], without reading them into
+/// one `String` first. Both inputs are still kept in memory, one `String` per line, because the
+/// diff needs all of them. Lines must be valid UTF-8, `\r\n` line endings are stripped too.
+pub fn diff_lines_reader<R: BufRead>(old: R, new: R) -> io::Result<LineChangesetOwned> {
+    fn read_lines<R: BufRead>(mut reader: R) -> io::Result<(Vec<String>, bool)> {
+        let mut lines = Vec::new();
+        let mut newline_at_end = true;
+        let mut line = String::new();
+        while reader.read_line(&mut line)? > 0 {
+            newline_at_end = line.ends_with('\n');
+            let content = line.trim_end_matches('\n');
+            lines.push(content.strip_suffix('\r').unwrap_or(content).to_string());
+            line.clear();
+        }
+        Ok((lines, newline_at_end))
+    }
+    let (old, old_newline_at_end) = read_lines(old)?;
+    let (new, new_newline_at_end) = read_lines(new)?;
+    let mut settings = LineChangeset::new(Vec::new(), Vec::new());
+    settings.old_newline_at_end = old_newline_at_end;
+    settings.new_newline_at_end = new_newline_at_end;
+    Ok(LineChangesetOwned {
+        old,
+        new,
+        names: None,
+        settings,
+    })
+}
+
+/// Lines of one variant aligned to the base, see [`MultiLineChangeset`]
+#[cfg(feature = "table")]
+#[derive(Debug)]
+struct AlignedVariant<'a> {
+    /// Lines inserted before each base line, the last entry holds lines after the base
+    inserted: Vec<Vec<(usize, &'a str)>>,
+    /// Line matched with each base line and whether it differs, `None` if removed
+    matched: Vec<Option<(usize, &'a str, bool)>>,
+}
+
+/// Line-by-line diff of several variants against a common base, shown as a table with one
+/// column per input. Can be pretty-printed by Display trait.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultiLineChangeset<'a> {
+    base: Vec<&'a str>,
+    variants: Vec<Vec<&'a str>>,
+    names: Vec<&'a str>,
+    show_lines: bool,
+    color: ColorMode,
+}
+
+impl<'a> MultiLineChangeset<'a> {
+    pub fn new(base: Vec<&'a str>, variants: Vec<Vec<&'a str>>) -> MultiLineChangeset<'a> {
+        MultiLineChangeset {
+            base,
+            variants,
+            names: vec![],
+            show_lines: true,
+            color: ColorMode::default(),
+        }
+    }
+
+    /// Sets column names, base first
+    pub fn names(mut self, names: Vec<&'a str>) -> Self {
+        self.names = names;
+        self
+    }
+    /// Show line numbers
+    pub fn set_show_lines(mut self, val: bool) -> Self {
+        self.show_lines = val;
+        self
+    }
+    /// Use colors in output (default unless `NO_COLOR` is set)
+    pub fn set_color(mut self, val: bool) -> Self {
+        self.color = if val {
+            ColorMode::Always
+        } else {
+            ColorMode::Never
+        };
+        self
+    }
+    /// When to use colors, see [`ColorMode`]
+    pub fn set_color_mode(mut self, val: ColorMode) -> Self {
+        self.color = val;
+        self
+    }
+
+    /// Returns changes of every variant against the base
+    pub fn diffs(&self) -> Vec<Vec<basic::DiffOp<'_, &'a str>>> {
+        self.variants
+            .iter()
+            .map(|variant| basic::diff(&self.base, variant))
+            .collect()
+    }
+
+    #[cfg(feature = "table")]
+    fn align(&self, variant: &[&'a str]) -> AlignedVariant<'a> {
+        let mut aligned = AlignedVariant {
+            inserted: vec![vec![]; self.base.len() + 1],
+            matched: vec![None; self.base.len()],
+        };
+        let (mut i, mut j) = (0, 0);
+        for op in basic::diff(&self.base, variant) {
+            let (old, new) = op_sides(&op);
+            let equal = matches!(op, basic::DiffOp::Equal(_));
+            let paired = min(old.len(), new.len());
+            for (k, line) in new.iter().enumerate().take(paired) {
+                aligned.matched[i + k] = Some((j + k + 1, line, !equal));
+            }
+            for (k, line) in new.iter().enumerate().skip(paired) {
+                aligned.inserted[i + old.len()].push((j + k + 1, line));
+            }
+            i += old.len();
+            j += new.len();
+        }
+        aligned
+    }
+
+    /// Rows of `(line number, text)` cells, base first. Variants of different lengths are
+    /// padded with empty cells.
+    #[cfg(feature = "table")]
+    fn rows(&self) -> Vec<Vec<(Option<usize>, String)>> {
+        let aligned: Vec<_> = self.variants.iter().map(|v| self.align(v)).collect();
+        let empty = || (None, String::new());
+        let mut rows = vec![];
+        for k in 0..=self.base.len() {
+            let inserted = aligned
+                .iter()
+                .map(|a| a.inserted[k].len())
+                .max()
+                .unwrap_or(0);
+            for n in 0..inserted {
+                let mut row = vec![empty()];
+                row.extend(aligned.iter().map(|a| match a.inserted[k].get(n) {
+                    Some(&(line, text)) => (Some(line), self.insert_color(text)),
+                    None => empty(),
+                }));
+                rows.push(row);
+            }
+            if k == self.base.len() {
+                break;
+            }
+            let mut row = vec![(Some(k + 1), self.base[k].to_string())];
+            row.extend(aligned.iter().map(|a| match a.matched[k] {
+                Some((line, text, true)) => (Some(line), self.insert_color(text)),
+                Some((line, text, false)) => (Some(line), text.to_string()),
+                None => empty(),
+            }));
+            rows.push(row);
+        }
+        rows
+    }
+
+    #[cfg(feature = "table")]
+    fn insert_color(&self, s: &str) -> String {
+        #[cfg(feature = "ansi")]
+        if self.color.enabled() {
+            return s.green().to_string();
+        }
+        s.to_string()
+    }
+
+    #[cfg(feature = "table")]
+    fn prettytable_mktable(&self) -> prettytable::Table {
+        let mut table = format_table::new();
+        if !self.names.is_empty() {
+            let mut header = vec![];
+            for name in &self.names {
+                if self.show_lines {
+                    header.push(Cell::new(""));
+                }
+                let name = name.to_string();
+                #[cfg(feature = "ansi")]
+                let name = if self.color.enabled() {
+                    name.cyan().to_string()
+                } else {
+                    name
+                };
+                header.push(Cell::new(&name));
+            }
+            table.set_titles(Row::new(header));
+        }
+        for row in self.rows() {
+            let mut cells = vec![];
+            for (line, text) in row {
+                if self.show_lines {
+                    cells.push(Cell::new(&line.map_or_else(String::new, |l| l.to_string())));
+                }
+                cells.push(Cell::new(&text));
+            }
+            table.add_row(Row::new(cells));
+        }
+        table
+    }
+
+    /// Prints table with base and variants
+    #[cfg(feature = "table")]
+    pub fn prettytable(&self) {
+        let table = self.prettytable_mktable();
+        table.printstd();
+    }
+
+    /// Returns table with base and variants
+    #[cfg(feature = "table")]
+    pub fn prettytable_string(&self) -> String {
+        self.prettytable_mktable().to_string()
+    }
+}
+
+#[cfg(feature = "table")]
+impl<'a> fmt::Display for MultiLineChangeset<'a> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{}", self.prettytable_mktable())
+    }
+}
+
+/// Diff several variants against `base` line by line
+pub fn diff_lines_multi<'a>(base: &'a str, variants: &[&'a str]) -> MultiLineChangeset<'a> {
+    MultiLineChangeset::new(
+        base.lines().collect(),
+        variants.iter().map(|v| v.lines().collect()).collect(),
+    )
+}
+
+fn _test_splitter_basic(text: &str, exp: &[&str]) {
+    let res =
+        collect_strings(split_by_char_fn(text, |c: char| c.is_whitespace()).map(|s| s.to_string()));
+    assert_eq!(res, exp)
+}
+
+#[test]
+fn test_splitter() {
+    _test_splitter_basic(
+        "  blah test2 test3  ",
         &[" ", " ", "blah", " ", "test2", " ", "test3", " ", " "],
     );
-    _test_splitter_basic(
-        "\tblah test2 test3  ",
-        &["\t", "blah", " ", "test2", " ", "test3", " ", " "],
+    _test_splitter_basic(
+        "\tblah test2 test3  ",
+        &["\t", "blah", " ", "test2", " ", "test3", " ", " "],
+    );
+    _test_splitter_basic(
+        "\tblah test2 test3  t",
+        &["\t", "blah", " ", "test2", " ", "test3", " ", " ", "t"],
+    );
+    _test_splitter_basic(
+        "\tblah test2 test3  tt",
+        &["\t", "blah", " ", "test2", " ", "test3", " ", " ", "tt"],
+    );
+}
+
+#[cfg(feature = "ansi")]
+#[test]
+fn test_basic() {
+    println!("diff_chars: {}", diff_chars("abefcd", "zadqwc"));
+    println!(
+        "diff_chars: {}",
+        diff_chars(
+            "The quick brown fox jumps over the lazy dog",
+            "The quick brown dog leaps over the lazy cat"
+        )
+    );
+    println!(
+        "diff_chars: {}",
+        diff_chars(
+            "The red brown fox jumped over the rolling log",
+            "The brown spotted fox leaped over the rolling log"
+        )
+    );
+    println!(
+        "diff_chars: {}",
+        diff_chars(
+            "The red    brown fox jumped over the rolling log",
+            "The brown spotted fox leaped over the rolling log"
+        )
+        .set_highlight_whitespace(true)
+    );
+    println!(
+        "diff_words: {}",
+        diff_words(
+            "The red brown fox jumped over the rolling log",
+            "The brown spotted fox leaped over the rolling log"
+        )
+    );
+    println!(
+        "diff_words: {}",
+        diff_words(
+            "The quick brown fox jumps over the lazy dog",
+            "The quick, brown dog leaps over the lazy cat"
+        )
+    );
+}
+
+#[test]
+fn test_diff_words_by() {
+    let camel_case = |text: &'static str| -> Vec<&'static str> {
+        let mut tokens = vec![];
+        let mut start = 0;
+        for (index, c) in text.char_indices().skip(1) {
+            if c.is_uppercase() {
+                tokens.push(&text[start..index]);
+                start = index;
+            }
+        }
+        tokens.push(&text[start..]);
+        tokens
+    };
+    assert_eq!(
+        diff_words_by("getUserName", "getUserId", camel_case).diff(),
+        vec![
+            basic::DiffOp::Equal(&["get", "User"][..]),
+            basic::DiffOp::Replace(&["Name"][..], &["Id"][..]),
+        ]
+    );
+    assert_eq!(
+        diff_words_by("a b", "a c", |text| split_words(text).collect()),
+        diff_words("a b", "a c")
+    );
+}
+
+#[cfg(feature = "ansi")]
+#[test]
+fn test_whitespace_markers() {
+    let changeset = diff_words("a b", "a b \t").set_whitespace_markers(true);
+    let whitespace = Style::new().white().on_green();
+    assert_eq!(
+        changeset.format(),
+        format!("a b{}{}", "·".style(whitespace), "→".style(whitespace))
+    );
+    assert_eq!(
+        diff_words("x  y", "x y")
+            .set_whitespace_markers(true)
+            .set_color(false)
+            .format(),
+        "x [-·-]y"
+    );
+}
+
+#[test]
+fn test_get_close_matches() {
+    let words = ["ape", "apple", "peach", "puppy"];
+    assert_eq!(get_close_matches("appel", &words, 3, 0.6), ["apple", "ape"]);
+    assert_eq!(get_close_matches("appel", &words, 1, 0.6), ["apple"]);
+    assert_eq!(get_close_matches("appel", &words, 3, 0.0).len(), 3);
+    assert!(get_close_matches("xyz", &words, 3, 0.6).is_empty());
+}
+
+#[test]
+fn test_has_changes() {
+    assert!(!diff_lines("a\nb\n", "a\nb\n").has_changes());
+    assert!(diff_lines("a\nb\n", "a\nc\n").has_changes());
+    assert!(!diff_lines("a\n", "a\n\n")
+        .set_ignore_blank_lines(true)
+        .has_changes());
+    assert!(!diff_words("Hello", "hello")
+        .set_ignore_case(true)
+        .has_changes());
+    assert!(diff_chars("abc", "abd").has_changes());
+}
+
+#[test]
+fn test_separator() {
+    let changeset = InlineChangeset::new(vec!["a", "b", "c"], vec!["a", "x", "c", "d"])
+        .set_separator(", ")
+        .set_color(false);
+    assert_eq!(changeset.format(), "a, [-b-]{+x+}, c, {+d+}");
+    let changeset = InlineChangeset::new(vec!["a", "b", "c"], vec!["c"])
+        .set_separator(", ")
+        .set_color(false);
+    assert_eq!(changeset.format(), "[-a, b-], c");
+}
+
+#[cfg(feature = "ansi")]
+#[test]
+fn test_ignore_case() {
+    let changeset = diff_words("Hello World", "hello world!").set_ignore_case(true);
+    assert_eq!(
+        changeset.diff(),
+        vec![
+            basic::DiffOp::Equal(&["Hello", " ", "World"][..]),
+            basic::DiffOp::Insert(&["!"][..]),
+        ]
+    );
+    assert_eq!(
+        changeset.format(),
+        format!("Hello World{}", "!".style(Style::new().green()))
+    );
+    let lines = diff_lines("Hello\nA\n", "hello\nB\n").set_ignore_case(true);
+    assert_eq!(lines.diff()[0], basic::DiffOp::Equal(&["Hello"][..]));
+    assert_eq!(lines.stats().deletions, 1);
+}
+
+#[test]
+fn test_split_sentences() {
+    assert_eq!(
+        collect_strings(split_sentences("Hello World. How are you?  Fine!")),
+        ["Hello World. ", "How are you?  ", "Fine!"]
+    );
+    assert_eq!(
+        collect_strings(split_sentences("Version 1.2 is out, e.g. here.\nWow!! ")),
+        ["Version 1.2 is out, e.g. ", "here.\n", "Wow!! "]
+    );
+    assert_eq!(split_sentences("").count(), 0);
+    assert_eq!(
+        diff_sentences("One. Two. Three.", "One. Deux. Three.").diff(),
+        vec![
+            basic::DiffOp::Equal(&["One. "][..]),
+            basic::DiffOp::Replace(&["Two. "][..], &["Deux. "][..]),
+            basic::DiffOp::Equal(&["Three."][..]),
+        ]
+    );
+}
+
+#[test]
+fn test_split_words() {
+    assert_eq!(
+        collect_strings(split_words("Hello World")),
+        ["Hello", " ", "World"]
+    );
+    assert_eq!(
+        collect_strings(split_words("Hello😋World")),
+        ["Hello", "😋", "World"]
+    );
+    assert_eq!(
+        collect_strings(split_words(
+            "The red brown fox\tjumped, over the rolling log"
+        )),
+        [
+            "The", " ", "red", " ", "brown", " ", "fox", "\t", "jumped", ",", " ", "over", " ",
+            "the", " ", "rolling", " ", "log"
+        ]
+    );
+}
+
+#[cfg(feature = "table")]
+#[test]
+fn test_diff_lines() {
+    let code1_a = r#"
+void func1() {
+    x += 1
+}
+
+void func2() {
+    x += 2
+}
+    "#;
+    let code1_b = r#"
+void func1(a: u32) {
+    x += 1
+}
+
+void functhreehalves() {
+    x += 1.5
+}
+
+void func2() {
+    x += 2
+}
+
+void func3(){}
+"#;
+    println!("diff_lines:");
+    println!("{}", diff_lines(code1_a, code1_b));
+    println!("====");
+    diff_lines(code1_a, code1_b)
+        .names("left", "right")
+        .set_align_new_lines(true)
+        .prettytable();
+}
+
+#[cfg(feature = "ansi")]
+fn _test_colors(changeset: &InlineChangeset, exp: &[(Option<Style>, &str)]) {
+    let color_s: String = collect_strings(exp.iter().map(|(style_opt, s)| {
+        if let Some(style) = style_opt {
+            s.style(*style).to_string()
+        } else {
+            s.to_string()
+        }
+    }))
+    .join("");
+    assert_eq!(format!("{}", changeset), color_s);
+}
+
+#[cfg(feature = "ansi")]
+#[test]
+fn test_diff_words_issue_1() {
+    let insert_style = Style::new().green();
+    let insert_whitespace_style = Style::new().white().on_green();
+    let remove_style = Style::new().red().strikethrough();
+    let remove_whitespace_style = Style::new().white().on_red();
+    let d1 = diff_words(
+        "und meine Unschuld beweisen!",
+        "und ich werde meine Unschuld beweisen!",
+    )
+    .set_insert_style(insert_style)
+    .set_insert_whitespace_style(insert_whitespace_style)
+    .set_remove_style(remove_style)
+    .set_remove_whitespace_style(remove_whitespace_style);
+
+    println!("diff_words: {} {:?}", d1, d1.diff());
+
+    _test_colors(
+        &d1,
+        &[
+            (None, "und "),
+            (Some(insert_style), "ich"),
+            (Some(insert_whitespace_style), " "),
+            (Some(insert_style), "werde"),
+            (Some(insert_whitespace_style), " "),
+            (None, "meine Unschuld beweisen!"),
+        ],
+    );
+    _test_colors(
+        &d1.set_highlight_whitespace(false),
+        &[
+            (None, "und "),
+            (Some(insert_style), "ich werde "),
+            (None, "meine Unschuld beweisen!"),
+        ],
+    );
+    let d2 = diff_words(
+        "Campaignings aus dem Ausland gegen meine Person ausfindig",
+        "Campaignings ausfindig",
+    );
+    println!("diff_words: {} {:?}", d2, d2.diff());
+    _test_colors(
+        &d2,
+        &[
+            (None, "Campaignings "),
+            (Some(remove_style), "aus"),
+            (Some(remove_whitespace_style), " "),
+            (Some(remove_style), "dem"),
+            (Some(remove_whitespace_style), " "),
+            (Some(remove_style), "Ausland"),
+            (Some(remove_whitespace_style), " "),
+            (Some(remove_style), "gegen"),
+            (Some(remove_whitespace_style), " "),
+            (Some(remove_style), "meine"),
+            (Some(remove_whitespace_style), " "),
+            (Some(remove_style), "Person"),
+            (Some(remove_whitespace_style), " "),
+            (None, "ausfindig"),
+        ],
+    );
+    let d3 = diff_words("des kriminellen Videos", "des kriminell erstellten Videos");
+    println!("diff_words: {} {:?}", d3, d3.diff());
+    _test_colors(
+        &d3,
+        &[
+            (None, "des "),
+            (Some(remove_style), "kriminellen"),
+            (Some(insert_style), "kriminell"),
+            (None, " "),
+            (Some(insert_style), "erstellten"),
+            (Some(insert_whitespace_style), " "),
+            (None, "Videos"),
+        ],
+    );
+}
+
+#[test]
+fn test_prettytable_process() {
+    let d1 = diff_lines(
+        r#"line1
+        line2
+        line3
+        "#,
+        r#"line1
+        line2
+        line2.5
+        line3
+        "#,
+    );
+
+    println!("diff_lines: {} {:?}", d1, d1.diff());
+    assert_eq!(
+        d1.prettytable_process(&["a", "b", "c"], None, Markup::Ansi),
+        (String::from("a\nb\nc"), 0)
+    );
+    assert_eq!(
+        d1.prettytable_process(&["a", "b", "c", ""], None, Markup::Ansi),
+        (String::from("a\nb\nc"), 0)
+    );
+    assert_eq!(
+        d1.prettytable_process(&["", "a", "b", "c"], None, Markup::Ansi),
+        (String::from("a\nb\nc"), 1)
+    );
+    assert_eq!(
+        d1.prettytable_process(&["", "a", "b", "c", ""], None, Markup::Ansi),
+        (String::from("a\nb\nc"), 1)
+    );
+}
+
+#[test]
+fn test_format_with_context() {
+    let d = diff_lines(
+        r#"line1
+        line2
+        line3
+        line4
+        line5
+        line6
+        line7
+        line8
+        line9
+        line10
+        line11
+        line12"#,
+        r#"line1
+        line2
+        line4
+        line5
+        line6.5
+        line7
+        line8
+        line9
+        line10
+        line11.5
+        line12"#,
+    );
+    let context = |n| ContextConfig {
+        context_size: n,
+        skipping_marker: "...",
+    };
+    println!(
+        "diff_lines:\n{}\n{:?}",
+        d.format_with_context(Some(context(0)), true),
+        d.diff()
+    );
+    let formatted_none = d.format_with_context(None, true);
+    let formatted_some_0 = d.format_with_context(Some(context(0)), true);
+    let formatted_some_1 = d.format_with_context(Some(context(1)), true);
+    let formatted_some_2 = d.format_with_context(Some(context(2)), true);
+    // With a context of size 2, every line is present
+    assert_eq!(
+        formatted_none.lines().count(),
+        formatted_some_2.lines().count()
+    );
+    // with a context of size 1:
+    // * line 1 is replaced by '...' (-0 lines)
+    // * line 8-9 are replaced by '...' (-1 line)
+    assert_eq!(
+        formatted_none.lines().count() - 1,
+        formatted_some_1.lines().count()
+    );
+    // with a context of size 0:
+    // * lines 1-2 are replaced by '...' (-1 line)
+    // * lines 4-5 are replaced by '...' (-1 line)
+    // * lines 7-10 are replaced by '...' (-3 lines)
+    // * line 12 is replaced by '...' (-0 lines)
+    assert_eq!(
+        formatted_none.lines().count() - 5,
+        formatted_some_0.lines().count()
+    );
+}
+
+#[test]
+fn test_classify() {
+    assert_eq!(
+        diff_lines("a\nb\nc\nd\ne", "b\nc\nD\ne\na").classify(),
+        vec![ChangeKind::Move, ChangeKind::Modify, ChangeKind::Move]
+    );
+    assert_eq!(
+        diff_lines("a\nb\nc", "a\nc\nd").classify(),
+        vec![ChangeKind::Remove, ChangeKind::Add]
+    );
+    assert_eq!(diff_lines("a\nb", "a\nb").classify(), vec![]);
+}
+
+#[test]
+fn test_normalize_line_endings() {
+    let crlf = LineChangeset::new(vec!["a\r", "b\r", "c"], vec!["a", "b", "c\r"]);
+    assert_eq!(
+        crlf.diff(),
+        vec![basic::DiffOp::Replace(
+            &["a\r", "b\r", "c"],
+            &["a", "b", "c\r"]
+        )]
+    );
+    assert_eq!(
+        crlf.set_normalize_line_endings(true).diff(),
+        vec![basic::DiffOp::Equal(&["a\r", "b\r", "c"])]
+    );
+    // `str::lines` already strips `\r\n`, only a lone `\r` needs normalizing
+    let mixed = diff_lines("a\r\nb\r\n", "a\nb\n");
+    assert_eq!(mixed.diff(), vec![basic::DiffOp::Equal(&["a", "b"])]);
+    assert_eq!(mixed.set_normalize_line_endings(true).format_unified(3), "");
+}
+
+#[cfg(feature = "ansi")]
+#[test]
+fn test_fold_blank_changes() {
+    let d = diff_lines("a\nb", "a\n\n\n\nb\n\nc").set_fold_blank_changes(true);
+    let lines: Vec<_> = d.format().lines().map(String::from).collect();
+    assert_eq!(lines.len(), 5);
+    assert_eq!(lines[1], format!(" {}", "+3 blank lines".green()));
+    assert_eq!(lines[3], format!(" {}", "".green()));
+    assert_eq!(d.set_fold_blank_changes(false).format().lines().count(), 7);
+}
+
+#[cfg(all(feature = "ansi", feature = "table"))]
+#[test]
+fn test_reversed_columns() {
+    let d = diff_lines("a\nb", "a\nc").names("left", "right");
+    let normal = d.prettytable_mktable().to_string();
+    let d = d.set_reversed_columns(true);
+    let reversed = d.prettytable_mktable().to_string();
+    assert!(normal.find("left").unwrap() < normal.find("right").unwrap());
+    assert!(reversed.find("right").unwrap() < reversed.find("left").unwrap());
+    let row = |table: &str| table.lines().find(|l| l.contains('b')).unwrap().to_string();
+    assert!(row(&normal).find('b').unwrap() < row(&normal).find('c').unwrap());
+    assert!(row(&reversed).find('c').unwrap() < row(&reversed).find('b').unwrap());
+    assert!(row(&reversed).contains(&"c".green().to_string()));
+}
+
+#[test]
+fn test_buffer_edits() {
+    let old = "The quick brown fox";
+    let new = "The quack brown föx!";
+    let edits = buffer_edits(old, new);
+    assert_eq!(
+        edits,
+        vec![
+            (6..7, "a".to_string()),
+            (17..18, "ö".to_string()),
+            (19..19, "!".to_string()),
+        ]
+    );
+    let mut buffer = old.to_string();
+    for (range, text) in edits.into_iter().rev() {
+        buffer.replace_range(range, &text);
+    }
+    assert_eq!(buffer, new);
+    assert_eq!(buffer_edits("same", "same"), vec![]);
+}
+
+#[cfg(feature = "ansi")]
+#[test]
+fn test_whitespace_changes() {
+    assert_eq!(
+        diff_words("    foo", "\tfoo").whitespace_changes(),
+        vec![WhitespaceChange::SpacesToTabs]
+    );
+    assert_eq!(
+        diff_words("foo\tbar", "foo bar").whitespace_changes(),
+        vec![WhitespaceChange::TabsToSpaces]
+    );
+    assert_eq!(
+        diff_words("foo bar", "foo   bar").whitespace_changes(),
+        vec![WhitespaceChange::Count]
+    );
+    assert_eq!(
+        diff_words("foo", "  foo  ").whitespace_changes(),
+        vec![WhitespaceChange::Leading, WhitespaceChange::Trailing]
+    );
+    assert_eq!(
+        diff_words("foo bar", "foo\nbar").whitespace_changes(),
+        vec![WhitespaceChange::Other]
+    );
+    assert_eq!(
+        diff_words("foo bar", "foo baz").whitespace_changes(),
+        vec![]
+    );
+    let formatted = diff_words("foo ", "foo")
+        .set_annotate_whitespace(true)
+        .format();
+    assert!(formatted.contains(&"[trailing whitespace]".dimmed().to_string()));
+}
+
+#[test]
+fn test_shift_changes() {
+    let old = "}\n\nfn b() {\n}";
+    let new = "}\n\nfn a() {\n}\n\nfn b() {\n}";
+    assert_eq!(
+        diff_lines(old, new)
+            .set_shift_changes(basic::ShiftDirection::Down)
+            .diff(),
+        vec![
+            basic::DiffOp::Equal(&["}", ""]),
+            basic::DiffOp::Insert(&["fn a() {", "}", ""]),
+            basic::DiffOp::Equal(&["fn b() {", "}"]),
+        ]
+    );
+}
+
+#[test]
+fn test_chunked_diff_words() {
+    let old = "lorem ipsum dolor sit amet ".repeat(200);
+    let new = old.replacen("dolor", "color", 1) + "end";
+    let full = diff_words(&old, &new);
+    let chunked = diff_words(&old, &new).set_chunk_size(64);
+    assert_eq!(chunked.format(), full.format());
+    // Long inputs are only chunked on request
+    assert_eq!(full.diff(), basic::diff(&full.old, &full.new));
+}
+
+#[test]
+fn test_context_diff() {
+    let d = diff_lines("a\nb\nc\nd\ne\nf\ng\nh", "a\nB\nc\ne\nf\ng\nh\ni").names("x.txt", "y.txt");
+    assert_eq!(
+        d.context_diff(1),
+        r#"*** x.txt
+--- y.txt
+***************
+*** 1,5 ****
+  a
+! b
+  c
+- d
+  e
+--- 1,4 ----
+  a
+! B
+  c
+  e
+***************
+*** 8 ****
+--- 7,8 ----
+  h
++ i
+"#
+    );
+    assert_eq!(d.context_diff(3).matches("***************").count(), 1);
+    assert_eq!(diff_lines("a", "a").context_diff(3), "");
+}
+
+#[test]
+fn test_stats_moved() {
+    assert_eq!(
+        diff_lines("f1\nf2\na\nb\nc\nd\ne", "a\nB\nc\nd\ne\nf1\nf2").stats(),
+        basic::DiffStats {
+            insertions: 1,
+            deletions: 1,
+            unchanged: 4,
+            moved: 2,
+            replacements: 1,
+        }
+    );
+}
+
+#[cfg(all(feature = "ansi", feature = "table"))]
+#[test]
+fn test_max_refine_depth() {
+    let refine = |depth| {
+        diff_lines("", "").set_max_refine_depth(depth).refine(
+            "a color",
+            "a colour",
+            0,
+            Markup::Ansi,
+        )
+    };
+    assert_eq!(
+        refine(0),
+        ("a color".red().to_string(), "a colour".green().to_string())
+    );
+    assert_eq!(
+        refine(1),
+        (
+            format!("a {}", "color".red()),
+            format!("a {}", "colour".green())
+        )
+    );
+    assert_eq!(
+        refine(2),
+        ("a color".to_string(), format!("a colo{}r", "u".green()))
+    );
+    assert_eq!(refine(5), refine(2));
+}
+
+#[test]
+fn test_diff_lines_by_key() {
+    let old = "id,amount\n1,\"1,000\"\n2,\"25\"\n";
+    let new = "id,amount\n1,\"1000\"\n2,\"30\"\n";
+    let d = diff_lines_by_key(old, new, |line| line.replace(',', ""));
+    assert_eq!(
+        d.diff(),
+        vec![
+            basic::DiffOp::Equal(&["id,amount", "1,\"1,000\""][..]),
+            basic::DiffOp::Replace(&["2,\"25\""][..], &["2,\"30\""][..]),
+        ]
+    );
+    assert_eq!(diff_lines(old, new).diff().len(), 2);
+    assert!(
+        diff_lines_by_key("1,000", "1000", |line| line.replace(',', ""))
+            .diff()
+            .iter()
+            .all(|op| !op.is_change())
+    );
+}
+
+#[test]
+fn test_line_key_across_threads() {
+    let d = diff_lines("a\nB\nc", "a\nb\nC").set_line_key(|line| line.to_lowercase());
+    let ops = std::thread::scope(|scope| scope.spawn(|| d.diff().len()).join().unwrap());
+    assert_eq!(ops, 1);
+}
+
+#[test]
+fn test_word_eq() {
+    let british = |word: &str| word.replace("our", "or");
+    let d = diff_words("the color of honour", "the colour of honor")
+        .set_word_eq(move |a, b| british(a) == british(b));
+    assert_eq!(d.diff().len(), 1);
+    assert_eq!(d.format(), "the color of honour");
+}
+
+#[test]
+fn test_changesets_send_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<InlineChangeset<'static>>();
+    assert_send_sync::<LineChangeset<'static>>();
+    assert_send_sync::<InlineChangesetOwned>();
+    assert_send_sync::<LineChangesetOwned>();
+}
+
+#[test]
+fn test_format_unified() {
+    let old = "a\nb\nc\nd\ne\nf\ng\nh\n";
+    let new = "a\nB\nc\nd\ne\nf\ng\nH\n";
+    assert_eq!(
+        diff_lines(old, new)
+            .names("a.txt", "b.txt")
+            .format_unified(1),
+        "--- a.txt\n+++ b.txt\n@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n@@ -7,2 +7,2 @@\n g\n-h\n+H\n"
+    );
+    assert_eq!(
+        diff_lines(old, new).format_unified(3),
+        "--- old\n+++ new\n@@ -1,8 +1,8 @@\n a\n-b\n+B\n c\n d\n e\n f\n g\n-h\n+H\n"
+    );
+    assert_eq!(diff_lines(old, old).format_unified(3), "");
+    assert_eq!(
+        diff_lines("", "x\n").format_unified(3),
+        "--- old\n+++ new\n@@ -0,0 +1 @@\n+x\n"
+    );
+}
+
+#[test]
+fn test_format_unified_file_bounds() {
+    let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\n";
+    let new = "A\nb\nc\nd\ne\nf\ng\nh\nI\n";
+    assert_eq!(
+        diff_lines(old, new).format_unified(3),
+        "--- old\n+++ new\n@@ -1,4 +1,4 @@\n-a\n+A\n b\n c\n d\n@@ -6,4 +6,4 @@\n f\n g\n h\n-i\n+I\n"
+    );
+    assert_eq!(
+        diff_lines("a\nb\n", "x\na\nb\n").format_unified(0),
+        "--- old\n+++ new\n@@ -0,0 +1 @@\n+x\n"
+    );
+    assert_eq!(
+        diff_lines("a\nb\n", "a\n").format_unified(3),
+        "--- old\n+++ new\n@@ -1,2 +1 @@\n a\n-b\n"
     );
-    _test_splitter_basic(
-        "\tblah test2 test3  t",
-        &["\t", "blah", " ", "test2", " ", "test3", " ", " ", "t"],
+}
+
+#[test]
+fn test_track_final_newline() {
+    let d = diff_lines("a\nb\n", "a\nb");
+    assert!(!d.has_changes());
+    assert_eq!(d.stats().insertions, 0);
+    let d = d.set_track_final_newline(true);
+    assert!(d.has_changes());
+    assert_eq!(
+        d.diff(),
+        vec![
+            basic::DiffOp::Equal(&["a"][..]),
+            basic::DiffOp::Replace(&["b"][..], &["b"][..]),
+        ]
     );
-    _test_splitter_basic(
-        "\tblah test2 test3  tt",
-        &["\t", "blah", " ", "test2", " ", "test3", " ", " ", "tt"],
+    assert_eq!(
+        d.format_unified(1),
+        "--- old\n+++ new\n@@ -1,2 +1,2 @@\n a\n-b\n+b\n\\ No newline at end of file\n"
     );
+    assert!(!diff_lines("a\nb", "a\nb")
+        .set_track_final_newline(true)
+        .has_changes());
 }
 
 #[test]
-fn test_basic() {
-    println!("diff_chars: {}", diff_chars("abefcd", "zadqwc"));
-    println!(
-        "diff_chars: {}",
-        diff_chars(
-            "The quick brown fox jumps over the lazy dog",
-            "The quick brown dog leaps over the lazy cat"
-        )
+fn test_format_unified_no_newline() {
+    assert_eq!(
+        diff_lines("a\nb", "a\nb\n").format_unified(1),
+        "--- old\n+++ new\n@@ -1,2 +1,2 @@\n a\n-b\n\\ No newline at end of file\n+b\n"
     );
-    println!(
-        "diff_chars: {}",
-        diff_chars(
-            "The red brown fox jumped over the rolling log",
-            "The brown spotted fox leaped over the rolling log"
+    assert_eq!(
+        diff_lines("a\nb\n", "a\nb\nc").format_unified(0),
+        "--- old\n+++ new\n@@ -2,0 +3 @@\n+c\n\\ No newline at end of file\n"
+    );
+    assert_eq!(
+        diff_lines("a\nb", "A\nb").format_unified(1),
+        "--- old\n+++ new\n@@ -1,2 +1,2 @@\n-a\n+A\n b\n\\ No newline at end of file\n"
+    );
+}
+
+#[test]
+fn test_to_markdown() {
+    let changeset = diff_lines("a\nb\nc\n", "a\nB\nc\n").names("x.txt", "y.txt");
+    assert_eq!(
+        changeset.to_markdown(),
+        "```diff\n--- x.txt\n+++ y.txt\n a\n-b\n+B\n c\n```\n"
+    );
+    assert_eq!(
+        changeset.set_diff_only(true).to_markdown(),
+        "```diff\n--- x.txt\n+++ y.txt\n-b\n+B\n```\n"
+    );
+    assert_eq!(
+        diff_lines("```\n", "````\n").to_markdown(),
+        "`````diff\n--- old\n+++ new\n-```\n+````\n`````\n"
+    );
+    assert_eq!(diff_lines("a\n", "a\n").to_markdown(), "");
+}
+
+#[test]
+fn test_to_html() {
+    let html = diff_lines("a < b\nsame\n", "a > b\nsame\nnew & old\n")
+        .names("x", "y")
+        .to_html();
+    assert_eq!(
+        html,
+        "<table class=\"diff\">
+<tr><th></th><th>x</th><th></th><th>y</th></tr>
+<tr><td class=\"diff-lineno\">1</td><td class=\"diff-remove\">a <span class=\"diff-remove\">&lt;</span> b</td>\
+<td class=\"diff-lineno\">1</td><td class=\"diff-insert\">a <span class=\"diff-insert\">&gt;</span> b</td></tr>
+<tr><td class=\"diff-lineno\">2</td><td class=\"diff-equal\">same</td><td class=\"diff-lineno\">2</td><td class=\"diff-equal\">same</td></tr>
+<tr><td class=\"diff-lineno\">3</td><td></td><td class=\"diff-lineno\">3</td><td class=\"diff-insert\">new &amp; old</td></tr>
+</table>
+"
+    );
+    let html = diff_lines("a\nb\n", "a\nc\n")
+        .set_show_lines(false)
+        .set_diff_only(true)
+        .to_html();
+    assert_eq!(
+        html,
+        "<table class=\"diff\">
+<tr><td class=\"diff-remove\"><span class=\"diff-remove\">b</span></td><td class=\"diff-insert\"><span class=\"diff-insert\">c</span></td></tr>
+</table>
+"
+    );
+}
+
+#[cfg(all(feature = "ansi", feature = "table"))]
+#[test]
+fn test_prettytable_to() {
+    let mut out = Vec::new();
+    diff_lines("a\nb\n", "a\nc\n")
+        .set_show_lines(false)
+        .prettytable_to(&mut out)
+        .unwrap();
+    let out = String::from_utf8(out).unwrap().replace('\r', "");
+    assert_eq!(out.lines().count(), 5);
+    assert!(out.contains(&"b".red().to_string()));
+    assert!(out.contains(&"c".green().to_string()));
+}
+
+#[test]
+fn test_ratio() {
+    assert_eq!(diff_lines("", "").ratio(), 1.0);
+    assert_eq!(diff_lines("a\nb\n", "").ratio(), 0.0);
+    assert_eq!(diff_lines("a\nb\nc\nd\n", "a\nb\nc\nx\n").ratio(), 0.75);
+    assert_eq!(diff_chars("abcd", "abcd").ratio(), 1.0);
+    assert_eq!(diff_chars("", "abcd").ratio(), 0.0);
+    assert_eq!(diff_chars("abcd", "abed").ratio(), 0.75);
+}
+
+#[cfg(feature = "ansi")]
+#[test]
+fn test_line_styles() {
+    let insert = Style::new().blue();
+    let remove = Style::new().yellow().bold();
+    let d = diff_lines("a\nb\n", "a\nc\n")
+        .set_insert_style(insert)
+        .set_remove_style(remove);
+    assert_eq!(
+        d.format(),
+        format!("a\n {}\n {}", "b".style(remove), "c".style(insert))
+    );
+    assert_eq!(
+        d.prettytable_process(&["x", "y"], Some(Change::Insert), Markup::Ansi),
+        (format!("{}\n{}", "x".style(insert), "y".style(insert)), 0)
+    );
+    assert_eq!(
+        d.refine("a b", "a c", 0, Markup::Ansi),
+        (
+            format!("a {}", "b".style(remove)),
+            format!("a {}", "c".style(insert))
         )
     );
-    println!(
-        "diff_chars: {}",
-        diff_chars(
-            "The red    brown fox jumped over the rolling log",
-            "The brown spotted fox leaped over the rolling log"
+}
+
+#[cfg(feature = "ansi")]
+#[test]
+fn test_set_equal_style() {
+    let dimmed = Style::new().dimmed();
+    let d = diff_lines("a\nb\nc\n", "a\nB\nc\n").set_equal_style(Some(dimmed));
+    assert_eq!(
+        d.format(),
+        format!(
+            "{}\n {}\n {}\n{}",
+            "a".style(dimmed),
+            "b".red().strikethrough(),
+            "B".green(),
+            "c".style(dimmed)
         )
-        .set_highlight_whitespace(true)
     );
-    println!(
-        "diff_words: {}",
-        diff_words(
-            "The red brown fox jumped over the rolling log",
-            "The brown spotted fox leaped over the rolling log"
+    #[cfg(feature = "table")]
+    assert!(d
+        .prettytable_string()
+        .contains(&"a".style(dimmed).to_string()));
+    assert_eq!(
+        d.set_equal_style(None).format(),
+        diff_lines("a\nb\nc\n", "a\nB\nc\n").format()
+    );
+}
+
+#[cfg(feature = "ansi")]
+#[test]
+fn test_format_columns() {
+    let d = diff_lines("a\nb\nlong line\n", "a\nB\nx\n")
+        .names("old", "new")
+        .set_color(false);
+    assert_eq!(
+        d.format_columns(6),
+        "  old    |   new\n1 a      | 1 a\n2 b      | 2 B\n3 long … | 3 x\n"
+    );
+    assert_eq!(
+        d.set_show_lines(false).format_columns(3),
+        "old | new\na   | a\nb   | B\nlo… | x\n"
+    );
+    let colored = diff_lines("a\n", "b\n")
+        .set_show_lines(false)
+        .format_columns(2);
+    assert_eq!(
+        colored,
+        format!("{}\x1b[0m  | {}\x1b[0m\n", "a".red(), "b".green())
+    );
+}
+
+#[test]
+fn test_char_stats() {
+    assert_eq!(
+        diff_words("the cat sat", "the dog sat").char_stats(),
+        (3, 3)
+    );
+    assert_eq!(diff_chars("kitten", "sitting").char_stats(), (3, 2));
+    assert_eq!(diff_chars("naïve", "naive").char_stats(), (1, 1));
+    assert_eq!(diff_words("a b", "a b").char_stats(), (0, 0));
+}
+
+#[test]
+fn test_reconstruct() {
+    let cases = [
+        diff_chars("kitten", "sitting"),
+        diff_words("the quick  fox", "a quick\tdog"),
+        diff_words_punctuated("Hello, world!", "Hello, there!"),
+        InlineChangeset::from_tokens(vec!["a", "b"], vec!["b", "c"], ", "),
+    ];
+    let expected = [
+        ("kitten", "sitting"),
+        ("the quick  fox", "a quick\tdog"),
+        ("Hello, world!", "Hello, there!"),
+        ("a, b", "b, c"),
+    ];
+    for (changeset, (old, new)) in cases.iter().zip(expected.iter()) {
+        assert_eq!(changeset.reconstruct_old(), *old);
+        assert_eq!(changeset.reconstruct_new(), *new);
+    }
+}
+
+#[cfg(feature = "ansi")]
+#[test]
+fn test_nested_char_highlight() {
+    let d = diff_words("the color red", "the colour blue")
+        .set_color(false)
+        .set_nested_char_highlight(true);
+    assert_eq!(d.format(), "the colo{+u+}r [-red-]{+blue+}");
+    assert_eq!(
+        diff_words("color", "colour")
+            .set_nested_char_highlight(true)
+            .format(),
+        format!("colo{}r", "u".style(Style::new().green()))
+    );
+}
+
+#[test]
+fn test_format_compact() {
+    let d = diff_chars("a\nb\\", "a\r\nc\\").set_color(false);
+    assert_eq!(d.format_compact(), "a{+\\r+}\\n[-b-]{+c+}\\\\");
+    assert!(!diff_words("a\nb", "a\nc").format_compact().contains('\n'));
+}
+
+#[cfg(feature = "ansi")]
+#[test]
+fn test_background_style() {
+    let insert = Style::new().black().on_green();
+    let d = diff_words("a", "a b c").set_insert_style(insert);
+    assert_eq!(d.format(), format!("a{}", " b c".style(insert)));
+    // Foreground-only styles still highlight whitespace separately
+    let d = diff_words("a", "a b").set_insert_style(Style::new().green());
+    assert_eq!(
+        d.format(),
+        format!(
+            "a{}{}",
+            " ".style(Style::new().white().on_green()),
+            "b".style(Style::new().green())
         )
     );
-    println!(
-        "diff_words: {}",
-        diff_words(
-            "The quick brown fox jumps over the lazy dog",
-            "The quick, brown dog leaps over the lazy cat"
+}
+
+#[cfg(feature = "ansi")]
+#[test]
+fn test_set_strikethrough() {
+    let d = diff_lines("a\nb\n", "a\nc\n").set_strikethrough(false);
+    assert_eq!(d.format(), format!("a\n {}\n {}", "b".red(), "c".green()));
+    let struck = Style::new().yellow().strikethrough();
+    assert_eq!(
+        d.set_remove_style(struck).format(),
+        format!("a\n {}\n {}", "b".style(Style::new().yellow()), "c".green())
+    );
+    let inline = diff_words("a b", "a c");
+    let (red, green) = (Style::new().red(), Style::new().green());
+    assert_eq!(
+        inline.clone().set_strikethrough(false).format(),
+        format!("a {}{}", "b".style(red), "c".style(green))
+    );
+    assert_eq!(
+        inline.format(),
+        format!("a {}{}", "b".style(red.strikethrough()), "c".style(green))
+    );
+}
+
+#[test]
+fn test_spans() {
+    use ChangeTag::*;
+    assert_eq!(
+        diff_words("a b c", "a x c").spans(),
+        vec![
+            (Equal, "a"),
+            (Equal, " "),
+            (Remove, "b"),
+            (Insert, "x"),
+            (Equal, " "),
+            (Equal, "c")
+        ]
+    );
+    assert_eq!(
+        diff_lines("a\nb\nc\n", "a\nB\nc\nd\n").spans(),
+        vec![
+            (Equal, "a"),
+            (Remove, "b"),
+            (Insert, "B"),
+            (Equal, "c"),
+            (Insert, "d")
+        ]
+    );
+}
+
+#[cfg(feature = "unicode")]
+#[test]
+fn test_diff_graphemes() {
+    let family = "\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}";
+    let couple = "\u{1f468}\u{200d}\u{1f469}";
+    let (old, new) = (format!("hi {}", family), format!("hi {}", couple));
+    let d = diff_graphemes(&old, &new);
+    assert_eq!(
+        d.diff(),
+        vec![
+            basic::DiffOp::Equal(&["h", "i", " "][..]),
+            basic::DiffOp::Replace(&[family][..], &[couple][..]),
+        ]
+    );
+    // Precomposed `é` and `e` + combining acute are different clusters, but never split
+    let d = diff_graphemes("caf\u{e9}", "cafe\u{301}");
+    assert_eq!(
+        d.diff(),
+        vec![
+            basic::DiffOp::Equal(&["c", "a", "f"][..]),
+            basic::DiffOp::Replace(&["\u{e9}"][..], &["e\u{301}"][..]),
+        ]
+    );
+    // Thai vowel signs and flags stay with their base
+    let d = diff_graphemes(
+        "\u{e01}\u{e31}\u{1f1fa}\u{1f1e6}",
+        "\u{e01}\u{1f1fa}\u{1f1e6}",
+    );
+    assert_eq!(
+        d.diff(),
+        vec![
+            basic::DiffOp::Replace(&["\u{e01}\u{e31}"][..], &["\u{e01}"][..]),
+            basic::DiffOp::Equal(&["\u{1f1fa}\u{1f1e6}"][..]),
+        ]
+    );
+}
+
+#[cfg(feature = "ansi")]
+#[test]
+fn test_prettytable_process_trims_blank_lines() {
+    let d = diff_lines("", "");
+    assert_eq!(
+        d.prettytable_process(&["", "", "a", "", "b", "", ""], None, Markup::Ansi),
+        (String::from("a\n\nb"), 2)
+    );
+    assert_eq!(
+        d.prettytable_process(&["", "", "", ""], None, Markup::Ansi),
+        (String::new(), 4)
+    );
+
+    let old = "\n\nold 1\nold 2\n\n\n";
+    let new = "\n\nnew 1\nnew 2\n\n\n";
+    let rows = diff_lines(old, new).side_by_side_rows(Markup::Ansi);
+    assert_eq!(rows.len(), 1);
+    assert_eq!((rows[0].old_line, rows[0].new_line), (3, 3));
+    assert_eq!(rows[0].old, format!("{} 1\n{} 2", "old".red(), "old".red()));
+    assert_eq!(
+        rows[0].new,
+        format!("{} 1\n{} 2", "new".green(), "new".green())
+    );
+}
+
+#[cfg(all(feature = "ansi", feature = "table"))]
+#[test]
+fn test_prettytable_string() {
+    let d = diff_lines("a\nb\n", "a\nc\n");
+    let mut out = Vec::new();
+    d.prettytable_to(&mut out).unwrap();
+    let table = d.prettytable_string();
+    assert_eq!(table.as_bytes(), &out[..]);
+    assert!(table.ends_with('\n'));
+    assert!(table.contains(&"c".green().to_string()));
+}
+
+#[cfg(feature = "ansi")]
+#[test]
+fn test_inline_replace_diff() {
+    let d = diff_lines("same\nold line\nx\n", "same\nnew line\ny\nadded\n");
+    assert_eq!(
+        d.inline_replace_diff(),
+        vec![(
+            format!("{} line\n{}", "old".red(), "x".red()),
+            format!(
+                "{} line\n{}\n{}",
+                "new".green(),
+                "y".green(),
+                "added".green()
+            )
+        )]
+    );
+    assert_eq!(
+        d.set_color(false).inline_replace_diff()[0],
+        ("old line\nx".to_string(), "new line\ny\nadded".to_string())
+    );
+}
+
+#[cfg(all(feature = "ansi", feature = "table"))]
+#[test]
+fn test_wrap_ansi() {
+    assert_eq!(wrap_ansi("abcdefg\nhi", 3), "abc\ndef\ng\nhi");
+    assert_eq!(wrap_ansi("abc", 3), "abc");
+    let red = "abcd".red().to_string();
+    assert_eq!(
+        wrap_ansi(&format!("x{}", red), 3),
+        format!(
+            "x{}\x1b[0m\n{}",
+            "ab".red().to_string().trim_end_matches("\x1b[39m"),
+            "cd".red()
         )
     );
+    // Nothing to restore after the colored part ended
+    assert_eq!(
+        wrap_ansi(&format!("{}xyz", "a".red()), 2),
+        format!("{}x\nyz", "a".red())
+    );
 }
 
+#[cfg(all(feature = "ansi", feature = "table"))]
 #[test]
-fn test_split_words() {
+fn test_truncate_ansi() {
+    assert_eq!(truncate_ansi("abcdef\nabc", 4), "abc…\nabc");
+    assert_eq!(truncate_ansi("abcd", 4), "abcd");
+    // Wide characters take two columns
+    assert_eq!(truncate_ansi("日本語テキスト", 5), "日本…");
     assert_eq!(
-        collect_strings(split_words("Hello World")),
-        ["Hello", " ", "World"]
+        truncate_ansi(&format!("{}xyz", "abcdef".red()), 4),
+        format!("{}", "abc…".red())
     );
+    assert_eq!(wrap_ansi("日本語", 4), "日本\n語");
+}
+
+#[cfg(feature = "table")]
+#[test]
+fn test_set_max_line_length() {
+    let table = diff_lines("short\n", "a much longer line\n")
+        .set_color(false)
+        .set_max_line_length(Some(8))
+        .prettytable_string();
+    assert!(table.contains("│ a much … │"));
+}
+
+#[cfg(feature = "table")]
+#[test]
+fn test_set_max_column_width() {
+    let table = diff_lines("short\n", "a much longer line\n")
+        .set_color(false)
+        .set_max_column_width(6)
+        .prettytable_string()
+        .replace('\r', "");
     assert_eq!(
-        collect_strings(split_words("Hello😋World")),
-        ["Hello", "😋", "World"]
+        table,
+        "\
+┌───┬───────┬───┬────────┐
+│ 1 │ short │ 1 │ a much │
+│   │       │   │  longe │
+│   │       │   │ r line │
+└───┴───────┴───┴────────┘
+"
     );
+}
+
+#[test]
+fn test_align_new_lines() {
+    let html = diff_lines("a\nold 1\nold 2\n", "a\nnew 1\nnew 2\nnew 3\n")
+        .set_align_new_lines(true)
+        .set_max_refine_depth(0)
+        .to_html();
+    let rows: Vec<_> = html
+        .lines()
+        .filter(|line| line.starts_with("<tr>"))
+        .collect();
+    assert_eq!(rows.len(), 4);
     assert_eq!(
-        collect_strings(split_words(
-            "The red brown fox\tjumped, over the rolling log"
-        )),
-        [
-            "The", " ", "red", " ", "brown", " ", "fox", "\t", "jumped", ",", " ", "over", " ",
-            "the", " ", "rolling", " ", "log"
+        rows[1],
+        "<tr><td class=\"diff-lineno\">2</td><td class=\"diff-remove\"><span class=\"diff-remove\">old 1</span></td>\
+         <td class=\"diff-lineno\">2</td><td class=\"diff-insert\"><span class=\"diff-insert\">new 1</span></td></tr>"
+    );
+    assert_eq!(
+        rows[3],
+        "<tr><td class=\"diff-lineno\">4</td><td></td>\
+         <td class=\"diff-lineno\">4</td><td class=\"diff-insert\">new 3</td></tr>"
+    );
+}
+
+#[test]
+fn test_set_line_offsets() {
+    let html = diff_lines("a\nb\n", "a\nc\n")
+        .set_line_offsets(500, 42)
+        .to_html();
+    assert!(html.contains("<td class=\"diff-lineno\">500</td><td class=\"diff-equal\">a</td>"));
+    assert!(html.contains("<td class=\"diff-lineno\">43</td><td class=\"diff-insert\">"));
+    assert!(html.contains("<td class=\"diff-lineno\">501</td><td class=\"diff-remove\">"));
+}
+
+#[test]
+fn test_set_context_lines() {
+    let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n";
+    let new = "1\n2\n3\nfour\n5\n6\n7\n8\nnine\n10\n";
+    let html = diff_lines(old, new).set_context_lines(1).to_html();
+    let rows: Vec<_> = html
+        .lines()
+        .filter(|line| line.starts_with("<tr>"))
+        .collect();
+    assert_eq!(rows.len(), 8);
+    assert_eq!(
+        rows[0],
+        "<tr><td class=\"diff-fold\" colspan=\"4\">⋮</td></tr>"
+    );
+    assert!(
+        rows[1].starts_with("<tr><td class=\"diff-lineno\">3</td><td class=\"diff-equal\">3</td>")
+    );
+    assert!(
+        rows[3].starts_with("<tr><td class=\"diff-lineno\">5</td><td class=\"diff-equal\">5</td>")
+    );
+    assert_eq!(
+        rows[4],
+        "<tr><td class=\"diff-fold\" colspan=\"4\">⋮</td></tr>"
+    );
+    assert!(
+        rows[5].starts_with("<tr><td class=\"diff-lineno\">8</td><td class=\"diff-equal\">8</td>")
+    );
+    assert!(rows[7]
+        .starts_with("<tr><td class=\"diff-lineno\">10</td><td class=\"diff-equal\">10</td>"));
+    // Short unchanged runs are kept
+    let html = diff_lines(old, new).set_context_lines(2).to_html();
+    assert_eq!(html.matches("diff-fold").count(), 1);
+}
+
+#[cfg(feature = "ansi")]
+#[test]
+fn test_set_context() {
+    let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n";
+    let new = "1\n2\n3\nfour\n5\n6\n7\n8\n9\n";
+    let d = diff_lines(old, new);
+    assert_eq!(d.format().lines().count(), 10);
+    let formatted = d.set_context(Some(1)).format();
+    assert_eq!(
+        formatted,
+        format!(
+            "...\n    3\n    {}\n    {}\n    5\n...",
+            "4".red().strikethrough(),
+            "four".green()
+        )
+    );
+}
+
+#[test]
+fn test_set_fold_marker() {
+    let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n";
+    let new = "1\n2\n3\nfour\n5\n6\n7\n8\n9\n";
+    let d = diff_lines(old, new).set_context(Some(1)).set_color(false);
+    let formatted = d
+        .clone()
+        .set_fold_marker_with(|n| format!("@@ skipped {} lines @@", n))
+        .format();
+    let lines: Vec<_> = formatted.lines().collect();
+    assert_eq!(lines[0], "@@ skipped 2 lines @@");
+    assert_eq!(lines[lines.len() - 1], "@@ skipped 4 lines @@");
+    let html = d.set_context_lines(1).set_fold_marker("<...>").to_html();
+    assert_eq!(html.matches("&lt;...&gt;").count(), 2);
+}
+
+#[cfg(feature = "table")]
+#[test]
+fn test_prettytable_wide_characters() {
+    use unicode_width::UnicodeWidthStr;
+    let table = diff_lines("日本語\ta\nabc\n😀x\n", "日本語\tb\nabc\nxx\n")
+        .set_color(false)
+        .prettytable_string();
+    let lines: Vec<_> = table.lines().collect();
+    assert_eq!(lines[1], "│ 1 │ 日本語  a │ 1 │ 日本語  b │");
+    assert!(lines.iter().all(|line| line.width() == lines[0].width()));
+}
+
+#[cfg(feature = "table")]
+#[test]
+fn test_table_metrics() {
+    let d = diff_lines("a\nb\n", "a\nchanged\n")
+        .names("old", "new")
+        .set_color(false);
+    let table = d.prettytable_string();
+    let metrics = d.table_metrics();
+    assert_eq!(metrics.rows, 2);
+    assert_eq!(metrics.height, table.lines().count());
+    assert_eq!(metrics.width, table.lines().next().unwrap().chars().count());
+    let wrapped = d.set_max_column_width(3).table_metrics();
+    assert_eq!(wrapped.rows, 2);
+    assert_eq!(wrapped.height, metrics.height + 2);
+    assert!(wrapped.width < metrics.width);
+}
+
+#[test]
+fn test_tab_width() {
+    assert_eq!(expand_tabs("\tx\nab\tc", 4), "    x\nab  c");
+    assert_eq!(expand_tabs("a\tb", 8), "a       b");
+    assert_eq!(expand_tabs("a\tb", 0), "a\tb");
+    assert_eq!(expand_tabs("日本\tb", 8), "日本    b");
+
+    let d = diff_lines("all:\n\tcc a.c\n", "all:\n\tcc b.c\n\tld\n");
+    let rows = |d: LineChangeset| {
+        d.side_by_side_rows(Markup::Html)
+            .into_iter()
+            .map(|row| (row.old, row.new))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        rows(d.set_tab_width(2)),
+        vec![
+            ("all:".to_string(), "all:".to_string()),
+            (
+                "  cc <span class=\"diff-remove\">a</span>.c".to_string(),
+                "  cc <span class=\"diff-insert\">b</span>.c<span class=\"diff-insert\">\n  ld</span>"
+                    .to_string()
+            ),
         ]
     );
+    let d = diff_lines("a\tb\n", "");
+    assert_eq!(rows(d.set_tab_width(0))[0].0, "a\tb");
+}
+
+#[test]
+fn test_ignore_blank_lines() {
+    let old = "first\n\nsecond\nthird\n";
+    let new = "first\n\n\n  \nsecond\nthird\n";
+    let d = diff_lines(old, new).set_ignore_blank_lines(true);
+    assert_eq!(d.stats().insertions, 0);
+    assert_eq!(d.stats().deletions, 0);
+    assert!(d.classify().is_empty());
+    assert_eq!(d.format_unified(1), "");
+    assert_eq!(d.context_diff(1), "");
+    assert_eq!(d.format(), "first\n\n\n  \nsecond\nthird");
+    assert_eq!(
+        d.side_by_side_rows(Markup::Ansi)
+            .iter()
+            .map(|row| row.old_class)
+            .collect::<Vec<_>>(),
+        vec!["diff-equal", "diff-equal"]
+    );
+
+    let d = diff_lines(old, new);
+    assert_eq!(d.stats().insertions, 2);
+    assert_eq!(d.classify(), vec![ChangeKind::Add]);
+
+    // Blank lines next to real changes are still shown in the hunk
+    let d = diff_lines("a\nb\n", "a\n\nc\n").set_ignore_blank_lines(true);
+    assert_eq!(d.stats().insertions, 2);
+    assert_eq!(
+        d.format_unified(0),
+        "--- old\n+++ new\n@@ -2 +2,2 @@\n-b\n+\n+c\n"
+    );
+}
+
+#[cfg(feature = "ansi")]
+#[test]
+fn test_line_colored_summary() {
+    let old = "a\nb\nc\nd\ne\nf\ng\nh\n";
+    let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\n";
+    let d = diff_lines(old, new);
+    assert_eq!(
+        d.colored_summary(1),
+        "1 file changed, 2 hunks, \x1b[32m+2\x1b[39m \x1b[31m-1\x1b[39m, 1 modified"
+    );
+    assert_eq!(
+        d.colored_summary(3),
+        "1 file changed, 1 hunk, \x1b[32m+2\x1b[39m \x1b[31m-1\x1b[39m, 1 modified"
+    );
+    assert_eq!(
+        diff_lines(old, old).colored_summary(3),
+        "0 files changed, 0 hunks, \x1b[32m+0\x1b[39m \x1b[31m-0\x1b[39m"
+    );
+}
+
+#[test]
+fn test_stats_mixed() {
+    let old = "keep\nremoved\nkeep\nold 1\nold 2\nkeep\n";
+    let new = "inserted\nkeep\nkeep\nnew\nkeep\n";
+    let stats = diff_lines(old, new).stats();
+    assert_eq!(
+        stats,
+        basic::DiffStats {
+            insertions: 2,
+            deletions: 3,
+            unchanged: 3,
+            moved: 0,
+            replacements: 1,
+        }
+    );
+    assert_eq!(stats.to_string(), "+2 -3");
 }
 
 #[test]
-fn test_diff_lines() {
-    let code1_a = r#"
-void func1() {
-    x += 1
-}
+fn test_set_color() {
+    let d = diff_words("the quick fox", "the slow fox").set_color(false);
+    assert_eq!(d.format(), "the [-quick-]{+slow+} fox");
+    let d = diff_chars("a b", "a\tb")
+        .set_annotate_whitespace(true)
+        .set_color(false);
+    assert!(!d.format().contains("\x1b["));
 
-void func2() {
-    x += 2
+    let d = diff_lines("a\nb\nc\n", "a\nB\nc\n")
+        .names("left", "right")
+        .set_color(false);
+    assert_eq!(d.format(), "a\n-b\n+B\nc");
+    assert!(!d.format_with_context(None, true).contains("\x1b["));
+    #[cfg(feature = "table")]
+    assert!(!d.prettytable_string().contains("\x1b["));
+    // There are never colors without the `ansi` feature
+    assert_eq!(
+        diff_lines("a\nb\n", "a\nB\n")
+            .prettytable_process(&["x"], Some(Change::Remove), Markup::Ansi)
+            .0
+            .contains("\x1b["),
+        cfg!(feature = "ansi")
+    );
 }
-    "#;
-    let code1_b = r#"
-void func1(a: u32) {
-    x += 1
+
+#[cfg(feature = "termcolor")]
+#[test]
+fn test_write_colored() {
+    use std::io::Write;
+    use termcolor::{Buffer, Color, ColorSpec, WriteColor};
+
+    let d = diff_lines("a\nb\nc\n", "a\nB\nc\n");
+    let mut buffer = Buffer::no_color();
+    d.write_colored(&mut buffer).unwrap();
+    assert_eq!(buffer.as_slice(), b"a\n-b\n+B\nc\n");
+
+    // Same escapes as writing with the expected styles directly
+    let mut expected = Buffer::ansi();
+    writeln!(expected, "a").unwrap();
+    write!(expected, " ").unwrap();
+    let mut remove = ColorSpec::new();
+    remove.set_fg(Some(Color::Red)).set_strikethrough(true);
+    expected.set_color(&remove).unwrap();
+    write!(expected, "b").unwrap();
+    expected.reset().unwrap();
+    write!(expected, "\n ").unwrap();
+    expected
+        .set_color(ColorSpec::new().set_fg(Some(Color::Green)))
+        .unwrap();
+    write!(expected, "B").unwrap();
+    expected.reset().unwrap();
+    write!(expected, "\nc\n").unwrap();
+    let mut buffer = Buffer::ansi();
+    d.write_colored(&mut buffer).unwrap();
+    assert_eq!(buffer.as_slice(), expected.as_slice());
+    assert!(String::from_utf8_lossy(buffer.as_slice()).contains("\x1b[9m"));
+
+    let mut buffer = Buffer::ansi();
+    d.set_color_mode(ColorMode::Never)
+        .write_colored(&mut buffer)
+        .unwrap();
+    assert_eq!(buffer.as_slice(), b"a\n-b\n+B\nc\n");
 }
 
-void functhreehalves() {
-    x += 1.5
+#[test]
+fn test_color_mode() {
+    let d = diff_lines("a\nb\n", "a\nc\n");
+    assert_eq!(
+        d.clone().set_color_mode(ColorMode::Never),
+        d.clone().set_color(false)
+    );
+    assert!(!d
+        .clone()
+        .set_color_mode(ColorMode::Never)
+        .format()
+        .contains("\x1b["));
+    assert_eq!(
+        d.clone()
+            .set_color_mode(ColorMode::Always)
+            .format()
+            .contains("\x1b["),
+        cfg!(feature = "ansi")
+    );
+    assert_eq!(diff_lines("a", "b").color, ColorMode::default());
+    assert!(!no_color(None));
+    assert!(!no_color(Some("".into())));
+    assert!(no_color(Some("1".into())));
+    assert!(ColorMode::Always.enabled_for(false));
+    assert!(!ColorMode::Never.enabled_for(true));
+    assert!(!ColorMode::Auto.enabled_for(false));
+    #[cfg(feature = "table")]
+    {
+        let auto = d.clone().set_color_mode(ColorMode::Auto);
+        assert!(!auto.prettytable_string().contains("\x1b["));
+        assert_eq!(
+            d.prettytable_string().contains("\x1b["),
+            cfg!(feature = "ansi")
+        );
+    }
+    let inline = diff_words("a b", "a c").set_color_mode(ColorMode::Never);
+    assert_eq!(inline.format(), "a [-b-]{+c+}");
 }
 
-void func2() {
-    x += 2
+#[cfg(feature = "ansi")]
+#[test]
+fn test_inline_highlight() {
+    let rows = |d: LineChangeset| {
+        let row = &d.side_by_side_rows(Markup::Ansi)[0];
+        (row.old.clone(), row.new.clone())
+    };
+    let d = diff_lines("let a = 1;\n", "let a = 2;\n");
+    assert_eq!(
+        rows(d.clone().set_inline_highlight(false)),
+        (
+            "let a = 1;".red().to_string(),
+            "let a = 2;".green().to_string()
+        )
+    );
+    assert_eq!(
+        rows(d.clone().set_inline_highlight(true)),
+        (
+            format!("let a = {};", "1".red()),
+            format!("let a = {};", "2".green())
+        )
+    );
+    assert_eq!(rows(d.clone()), rows(d.set_inline_highlight(true)));
 }
 
-void func3(){}
-"#;
-    println!("diff_lines:");
-    println!("{}", diff_lines(code1_a, code1_b));
-    println!("====");
-    diff_lines(code1_a, code1_b)
-        .names("left", "right")
-        .set_align_new_lines(true)
-        .prettytable();
+#[cfg(all(feature = "ansi", feature = "table"))]
+#[test]
+fn test_diff_lines_multi() {
+    let base = "host=a\nport=1\n";
+    let table = diff_lines_multi(base, &["host=a\nport=2\ndebug=1\n", "port=1\n"])
+        .names(vec!["base", "staging", "prod"])
+        .set_color(false)
+        .prettytable_string()
+        .replace('\r', "");
+    assert_eq!(
+        table,
+        "\
+┌───┬────────┬───┬─────────┬───┬────────┐
+│   │ base   │   │ staging │   │ prod   │
+├───┼────────┼───┼─────────┼───┼────────┤
+│ 1 │ host=a │ 1 │ host=a  │   │        │
+├───┼────────┼───┼─────────┼───┼────────┤
+│ 2 │ port=1 │ 2 │ port=2  │ 1 │ port=1 │
+├───┼────────┼───┼─────────┼───┼────────┤
+│   │        │ 3 │ debug=1 │   │        │
+└───┴────────┴───┴─────────┴───┴────────┘
+"
+    );
+    let colored = diff_lines_multi(base, &["host=a\nport=2\n"]).prettytable_string();
+    assert!(colored.contains(&"port=2".green().to_string()));
 }
 
-fn _test_colors(changeset: &InlineChangeset, exp: &[(Option<Style>, &str)]) {
-    let color_s: String = collect_strings(exp.iter().map(|(style_opt, s)| {
-        if let Some(style) = style_opt {
-            s.style(*style).to_string()
-        } else {
-            s.to_string()
-        }
-    }))
-    .join("");
-    assert_eq!(format!("{}", changeset), color_s);
+#[test]
+fn test_into_owned() {
+    let (old, new) = ("a b c\n".to_string(), "a x c\n".to_string());
+    let inline = diff_words(&old, &new).set_color(false);
+    let lines = diff_lines(&old, &new).names("old", "new").set_color(false);
+    let expected = (inline.format(), lines.format(), lines.to_markdown());
+
+    let inline = inline.into_owned();
+    let lines = lines.into_owned();
+    drop((old, new));
+    assert_eq!(inline.format(), expected.0);
+    assert_eq!(lines.format(), expected.1);
+    assert_eq!(lines.changeset().to_markdown(), expected.2);
+    assert_eq!(lines.to_string(), lines.changeset().to_string());
 }
 
+#[cfg(feature = "ansi")]
 #[test]
-fn test_diff_words_issue_1() {
-    let insert_style = Style::new().green();
-    let insert_whitespace_style = Style::new().white().on_green();
-    let remove_style = Style::new().red().strikethrough();
-    let remove_whitespace_style = Style::new().white().on_red();
-    let d1 = diff_words(
-        "und meine Unschuld beweisen!",
-        "und ich werde meine Unschuld beweisen!",
-    )
-    .set_insert_style(insert_style)
-    .set_insert_whitespace_style(insert_whitespace_style)
-    .set_remove_style(remove_style)
-    .set_remove_whitespace_style(remove_whitespace_style);
+fn test_min_inline_match() {
+    let changeset = diff_lines("fox jumps over dog\n", "cat leaps over bird\n");
+    let (old, _) = changeset.inline_replace_diff().pop().unwrap();
+    assert!(old.contains(" over "));
+    assert!(!old.contains(&"over".red().to_string()));
 
-    println!("diff_words: {} {:?}", d1, d1.diff());
+    let (old, new) = changeset
+        .set_min_inline_match(4)
+        .inline_replace_diff()
+        .pop()
+        .unwrap();
+    assert!(old.contains("over"));
+    assert_eq!(old.matches("\x1b[31m").count(), 1, "{:?}", old);
+    assert_eq!(new.matches("\x1b[32m").count(), 1, "{:?}", new);
 
-    _test_colors(
-        &d1,
-        &[
-            (None, "und "),
-            (Some(insert_style), "ich"),
-            (Some(insert_whitespace_style), " "),
-            (Some(insert_style), "werde"),
-            (Some(insert_whitespace_style), " "),
-            (None, "meine Unschuld beweisen!"),
-        ],
-    );
-    _test_colors(
-        &d1.set_highlight_whitespace(false),
-        &[
-            (None, "und "),
-            (Some(insert_style), "ich werde "),
-            (None, "meine Unschuld beweisen!"),
-        ],
+    let absorbed = absorb_short_equal(
+        &["a", "b", "c"],
+        &["a", "x", "c"],
+        &basic::diff(&["a", "b", "c"], &["a", "x", "c"]),
+        5,
     );
-    let d2 = diff_words(
-        "Campaignings aus dem Ausland gegen meine Person ausfindig",
-        "Campaignings ausfindig",
-    );
-    println!("diff_words: {} {:?}", d2, d2.diff());
-    _test_colors(
-        &d2,
-        &[
-            (None, "Campaignings "),
-            (Some(remove_style), "aus"),
-            (Some(remove_whitespace_style), " "),
-            (Some(remove_style), "dem"),
-            (Some(remove_whitespace_style), " "),
-            (Some(remove_style), "Ausland"),
-            (Some(remove_whitespace_style), " "),
-            (Some(remove_style), "gegen"),
-            (Some(remove_whitespace_style), " "),
-            (Some(remove_style), "meine"),
-            (Some(remove_whitespace_style), " "),
-            (Some(remove_style), "Person"),
-            (Some(remove_whitespace_style), " "),
-            (None, "ausfindig"),
-        ],
-    );
-    let d3 = diff_words("des kriminellen Videos", "des kriminell erstellten Videos");
-    println!("diff_words: {} {:?}", d3, d3.diff());
-    _test_colors(
-        &d3,
-        &[
-            (None, "des "),
-            (Some(remove_style), "kriminellen"),
-            (Some(insert_style), "kriminell"),
-            (None, " "),
-            (Some(insert_style), "erstellten"),
-            (Some(insert_whitespace_style), " "),
-            (None, "Videos"),
-        ],
+    assert_eq!(
+        absorbed,
+        vec![
+            basic::DiffOp::Equal(&["a"][..]),
+            basic::DiffOp::Replace(&["b"][..], &["x"][..]),
+            basic::DiffOp::Equal(&["c"][..]),
+        ]
     );
 }
 
 #[test]
-fn test_prettytable_process() {
-    let d1 = diff_lines(
-        r#"line1
-        line2
-        line3
-        "#,
-        r#"line1
-        line2
-        line2.5
-        line3
-        "#,
-    );
-
-    println!("diff_lines: {} {:?}", d1, d1.diff());
+fn test_change_markers() {
+    let changeset = diff_lines("a\nb\nc\nd\n", "a\nx\nc\ne\nf\n").set_change_markers(true);
+    for changeset in [changeset.clone(), changeset.set_color(false)] {
+        let format = changeset.format();
+        let markers: Vec<&str> = format.lines().map(|line| &line[..2]).collect();
+        assert_eq!(markers, ["  ", "~ ", "~ ", "  ", "~ ", "~ ", "~ "]);
+    }
     assert_eq!(
-        d1.prettytable_process(&["a", "b", "c"], None),
-        (String::from("a\nb\nc"), 0)
+        diff_lines("a\nb\n", "b\nc\n")
+            .set_change_markers(true)
+            .set_color(false)
+            .format(),
+        "- a\n  b\n+ c"
     );
+}
+
+#[cfg(feature = "table")]
+#[test]
+fn test_gutter() {
+    let changeset = diff_lines("a\nb\nc\n", "a\nx\nc\nd\n")
+        .set_color(false)
+        .set_show_lines(false)
+        .set_gutter(true);
+    let flags = |table: String| -> String {
+        table
+            .lines()
+            .filter(|line| line.starts_with('│'))
+            .map(|line| {
+                line.split('│')
+                    .nth(2)
+                    .unwrap()
+                    .trim()
+                    .chars()
+                    .next()
+                    .unwrap_or(' ')
+            })
+            .collect()
+    };
+    assert_eq!(flags(changeset.prettytable_string()), " | >");
     assert_eq!(
-        d1.prettytable_process(&["a", "b", "c", ""], None),
-        (String::from("a\nb\nc"), 0)
+        flags(
+            changeset
+                .clone()
+                .set_reversed_columns(true)
+                .prettytable_string()
+        ),
+        " | <"
     );
+    let custom = GutterFlags {
+        changed: '*',
+        ..GutterFlags::default()
+    };
+    let table = changeset
+        .set_gutter_flags(custom)
+        .set_show_lines(true)
+        .prettytable_string();
+    assert_eq!(table.lines().nth(3).unwrap(), "│ 2 │ b │ * │ 2 │ x │");
+}
+
+#[cfg(feature = "table")]
+#[test]
+fn test_reversed_columns_line_numbers() {
+    let d = diff_lines("a\nb\n", "x\na\nc\n")
+        .set_color(false)
+        .set_line_offsets(10, 20);
+    let rows = |d: &LineChangeset| -> Vec<String> {
+        d.prettytable_string()
+            .lines()
+            .filter(|line| line.starts_with('│'))
+            .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+            .collect()
+    };
     assert_eq!(
-        d1.prettytable_process(&["", "a", "b", "c"], None),
-        (String::from("a\nb\nc"), 1)
+        rows(&d),
+        [
+            "│ 10 │ │ 20 │ x │",
+            "│ 10 │ a │ 21 │ a │",
+            "│ 11 │ b │ 22 │ c │"
+        ]
     );
+    let reversed = d.set_reversed_columns(true);
     assert_eq!(
-        d1.prettytable_process(&["", "a", "b", "c", ""], None),
-        (String::from("a\nb\nc"), 1)
+        rows(&reversed),
+        [
+            "│ 20 │ x │ 10 │ │",
+            "│ 21 │ a │ 10 │ a │",
+            "│ 22 │ c │ 11 │ b │"
+        ]
     );
+    assert!(reversed
+        .set_color(true)
+        .to_html()
+        .contains("<td class=\"diff-insert\">x</td><td class=\"diff-lineno\">10</td><td></td>"));
+}
+
+/// Applies the hunks of a unified diff to `old` like `patch`, panics on any mismatch
+#[cfg(test)]
+fn apply_unified(old: &str, patch: &str) -> String {
+    let old: Vec<&str> = old.split_inclusive('\n').collect();
+    let mut lines = patch.lines().skip_while(|line| !line.starts_with("@@"));
+    let mut out: Vec<String> = Vec::new();
+    let mut i = 0;
+    let mut header = lines.next();
+    while let Some(hunk) = header {
+        let range = |side: char| -> (usize, usize) {
+            let range = hunk.split(' ').find(|part| part.starts_with(side)).unwrap();
+            let mut numbers = range[1..].split(',').map(|n| n.parse::<usize>().unwrap());
+            (numbers.next().unwrap(), numbers.next().unwrap_or(1))
+        };
+        let ((old_start, old_len), (new_start, new_len)) = (range('-'), range('+'));
+        let old_start = if old_len == 0 {
+            old_start
+        } else {
+            old_start - 1
+        };
+        assert!(old_start >= i, "overlapping hunk {}", hunk);
+        out.extend(old[i..old_start].iter().map(|line| line.to_string()));
+        i = old_start;
+        let new_start = if new_len == 0 {
+            new_start
+        } else {
+            new_start - 1
+        };
+        assert_eq!(out.len(), new_start, "wrong new start in {}", hunk);
+        let (mut old_seen, mut new_seen) = (0, 0);
+        let mut last = ' ';
+        header = None;
+        for line in lines.by_ref() {
+            if line.starts_with("@@") {
+                header = Some(line);
+                break;
+            }
+            let (sign, text) = line.split_at(1);
+            match sign {
+                " " | "-" => {
+                    assert_eq!(old[i].trim_end_matches('\n'), text, "in {}", hunk);
+                    if sign == " " {
+                        out.push(old[i].to_string());
+                        new_seen += 1;
+                    }
+                    i += 1;
+                    old_seen += 1;
+                }
+                "+" => {
+                    out.push(format!("{}\n", text));
+                    new_seen += 1;
+                }
+                "\\" => {
+                    if last == '+' {
+                        out.last_mut().unwrap().pop();
+                    } else {
+                        assert!(!old[i - 1].ends_with('\n'), "in {}", hunk);
+                    }
+                }
+                _ => panic!("unexpected line {:?}", line),
+            }
+            last = sign.chars().next().unwrap();
+        }
+        assert_eq!(
+            (old_seen, new_seen),
+            (old_len, new_len),
+            "wrong counts in {}",
+            hunk
+        );
+    }
+    out.extend(old[i..].iter().map(|line| line.to_string()));
+    out.concat()
 }
 
 #[test]
-fn test_format_with_context() {
-    let d = diff_lines(
-        r#"line1
-        line2
-        line3
-        line4
-        line5
-        line6
-        line7
-        line8
-        line9
-        line10
-        line11
-        line12"#,
-        r#"line1
-        line2
-        line4
-        line5
-        line6.5
-        line7
-        line8
-        line9
-        line10
-        line11.5
-        line12"#,
+fn test_to_git_patch() {
+    let patch = diff_lines("a\nb\nc\n", "a\nx\nc\n").to_git_patch("src/f.txt", "src/f.txt");
+    assert_eq!(
+        patch,
+        "diff --git a/src/f.txt b/src/f.txt\nindex 0000000..0000000 100644\n\
+--- a/src/f.txt\n+++ b/src/f.txt\n@@ -1,3 +1,3 @@\n a\n-b\n+x\n c\n"
     );
-    let context = |n| ContextConfig {
-        context_size: n,
-        skipping_marker: "...",
+    assert_eq!(diff_lines("a\n", "a\n").to_git_patch("f", "f"), "");
+
+    let numbered = |lines: std::ops::Range<usize>| -> String {
+        lines.map(|i| format!("line {}\n", i)).collect()
     };
-    println!(
-        "diff_lines:\n{}\n{:?}",
-        d.format_with_context(Some(context(0)), true),
-        d.diff()
+    let far_apart = (
+        numbered(0..40),
+        numbered(0..40)
+            .replace("line 2\n", "two\n")
+            .replace("line 20\n", "")
+            .replace("line 39\n", "line 39\nline 40\n"),
     );
-    let formatted_none = d.format_with_context(None, true);
-    let formatted_some_0 = d.format_with_context(Some(context(0)), true);
-    let formatted_some_1 = d.format_with_context(Some(context(1)), true);
-    let formatted_some_2 = d.format_with_context(Some(context(2)), true);
-    // With a context of size 2, every line is present
+    let cases = [
+        ("a\nb\n", "b\n"),
+        ("", "a\nb\n"),
+        ("a\nb\n", ""),
+        ("a\nb", "a\nb\n"),
+        ("a\nb\n", "a\nc"),
+        ("a\nb", "x\na\nb"),
+        ("x\n\n\ny\n", "x\n\ny\n\n"),
+        (far_apart.0.as_str(), far_apart.1.as_str()),
+    ];
+    for (old, new) in cases.iter() {
+        let patch = diff_lines(old, new).to_git_patch("f", "f");
+        assert_eq!(apply_unified(old, &patch), *new, "{}", patch);
+    }
+}
+
+#[test]
+fn test_split_words_punctuated() {
     assert_eq!(
-        formatted_none.lines().count(),
-        formatted_some_2.lines().count()
+        split_words_punctuated("(foo, bar.baz!) é...").collect::<Vec<_>>(),
+        vec!["(", "foo,", " ", "bar.", "baz!)", " ", "é..."]
     );
-    // with a context of size 1:
-    // * line 1 is replaced by '...' (-0 lines)
-    // * line 8-9 are replaced by '...' (-1 line)
     assert_eq!(
-        formatted_none.lines().count() - 1,
-        formatted_some_1.lines().count()
+        diff_words_punctuated("say foo, now", "say foo. now").diff(),
+        vec![
+            basic::DiffOp::Equal(&["say", " "][..]),
+            basic::DiffOp::Replace(&["foo,"][..], &["foo."][..]),
+            basic::DiffOp::Equal(&[" ", "now"][..]),
+        ]
     );
-    // with a context of size 0:
-    // * lines 1-2 are replaced by '...' (-1 line)
-    // * lines 4-5 are replaced by '...' (-1 line)
-    // * lines 7-10 are replaced by '...' (-3 lines)
-    // * line 12 is replaced by '...' (-0 lines)
+}
+
+#[test]
+fn test_hunk_header_hint() {
+    let old = "fn a() {\n    1\n}\n\nfn b() {\n    let x = 2;\n    x\n}\n";
+    let new = old.replace("2;", "3;");
+    let changeset = diff_lines(old, &new);
+    let header = |changeset: &LineChangeset| {
+        changeset
+            .format_unified(0)
+            .lines()
+            .find(|line| line.starts_with("@@"))
+            .unwrap()
+            .to_string()
+    };
+    assert_eq!(header(&changeset), "@@ -6 +6 @@");
+    let changeset = changeset.set_hunk_header_hint(|line| line.starts_with("fn "));
+    assert_eq!(header(&changeset), "@@ -6 +6 @@ fn b() {");
     assert_eq!(
-        formatted_none.lines().count() - 5,
-        formatted_some_0.lines().count()
+        header(
+            &diff_lines("x\nfn a\n", "y\nfn a\n")
+                .set_hunk_header_hint(|line| line.starts_with("fn "))
+        ),
+        "@@ -1 +1 @@"
     );
 }
+
+#[test]
+fn test_diff_lines_reader() {
+    let cases = [
+        ("a\nb\n", "a\nc\n"),
+        ("a\r\nb", "a\nb\n"),
+        ("", "x"),
+        ("a\n\n", "a\n"),
+    ];
+    for (old, new) in cases.iter() {
+        let changeset = diff_lines_reader(old.as_bytes(), new.as_bytes())
+            .unwrap()
+            .configure(|changeset| changeset.names("old", "new").set_color(false));
+        let expected = diff_lines(old, new).names("old", "new").set_color(false);
+        assert_eq!(changeset.changeset(), expected);
+        assert_eq!(
+            changeset.changeset().format_unified(3),
+            expected.format_unified(3)
+        );
+    }
+    assert!(diff_lines_reader(&b"\xff\n"[..], &b""[..]).is_err());
+}